@@ -0,0 +1,77 @@
+use chess::Board;
+use std::str::FromStr;
+use std::time::Duration;
+use crate::config::Config;
+use crate::engine::Engine;
+use crate::time_manager::TimeManager;
+
+const DEFAULT_DEPTH: u64 = 3;
+
+// Openings, middlegames and endgames with too many pieces for the tablebase
+const POSITIONS: [&str; 8] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3",
+    "rnbqkb1r/pp2pppp/3p1n2/8/3NP3/8/PPP2PPP/RNBQKB1R w KQkq - 1 5",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "r2q1rk1/ppp2ppp/2np1n2/2b1p1B1/2B1P1b1/2NP1N2/PPP2PPP/R2Q1RK1 w - - 0 8",
+    "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1B1PPP/R2QKB1R b KQ - 0 8",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "6k1/5pp1/7p/8/8/7P/5PP1/3R2K1 w - - 0 1",
+];
+
+/// `bench [depth] [--stats]`: searches fixed positions to a fixed depth
+/// without randomness. The node count only changes if the search or the
+/// configured depths do.
+pub fn run(args: &[String], config: &Config) -> Result<(), String> {
+    let show_stats = args.iter().any(|a| a == "--stats");
+    let depth = match args.iter().find(|a| *a != "--stats") {
+        Some(depth) => depth.parse::<u64>().ok().filter(|d| *d >= 1).ok_or("Usage: bench [depth] [--stats], the depth is at least 1")?,
+        None => DEFAULT_DEPTH,
+    };
+    let (nodes, time) = search(depth, show_stats, config)?;
+    println!();
+    println!("Nodes: {}", nodes);
+    println!("NPS: {:.0}", nodes as f64 / time.as_secs_f64().max(0.001));
+    Ok(())
+}
+
+// Searches every position and returns the nodes and the time of all of them
+fn search(depth: u64, show_stats: bool, config: &Config) -> Result<(u64, Duration), String> {
+    let mut nodes = 0;
+    let mut time = Duration::ZERO;
+    for (i, fen) in POSITIONS.iter().enumerate() {
+        let brett = Board::from_str(fen).map_err(|e| e.to_string())?;
+        let mut engine = Engine::new(brett);
+        engine.set_random(false);
+        engine.set_depth_limit(depth);
+        engine.set_tablebase(&config.tablebase.path);
+        let (chess_move, _, info) = engine.best_move(0, config.engine.depth, config.engine.max_depth, TimeManager::infinite()).ok_or(format!("No legal move in {}", fen))?;
+        println!("{:>2}/{}  {:<6} {:>10} nodes  {:.2} s", i + 1, POSITIONS.len(), chess_move, info.stats.nodes, info.time.as_secs_f64());
+        if show_stats {
+            println!("       {}", info.stats);
+        }
+        nodes += info.stats.nodes;
+        time += info.time;
+    }
+    Ok((nodes, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_nodes_every_run() {
+        let mut config = Config::default();
+        config.tablebase.path = String::new();
+        let (nodes, _) = search(1, false, &config).unwrap();
+        assert!(nodes > 0);
+        assert_eq!(search(1, false, &config).unwrap().0, nodes);
+    }
+
+    #[test]
+    fn depth_zero_is_rejected() {
+        assert!(run(&["0".to_string()], &Config::default()).is_err());
+        assert!(run(&["deep".to_string()], &Config::default()).is_err());
+    }
+}
@@ -0,0 +1,22 @@
+use schach2::bench;
+use schach2::config::Config;
+use schach2::epd;
+use schach2::match_runner;
+use schach2::perft;
+use schach2::tablebase;
+
+const USAGE: &str = "Usage: schach2-tools [--config <file>] [--<section>.<key>=<value>] <perft|divide|epd|bench|match|tablebase> [args]";
+
+fn main() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (config, args) = Config::from_args(&args)?;
+    config.init_global_thread_pool()?;
+    match args.first().map(String::as_str) {
+        Some(command @ ("perft" | "divide")) => perft::run(command, &args[1..]),
+        Some("epd") => epd::run(&args[1..], &config),
+        Some("bench") => bench::run(&args[1..], &config),
+        Some("match") => match_runner::run(&args[1..], &config),
+        Some("tablebase") => tablebase::run(&args[1..], &config),
+        _ => Err(USAGE.to_string()),
+    }
+}
@@ -0,0 +1,7 @@
+use schach2::config::Config;
+
+fn main() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (config, _) = Config::from_args(&args)?;
+    schach2::uci::run(config)
+}
@@ -0,0 +1,124 @@
+use chess::ChessMove;
+use chess::Color;
+use chess::Piece;
+use chess::Square;
+use sdl2::pixels;
+use sdl2::rect::Point;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::video::WindowContext;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use schach2::convert;
+use crate::texture_manager::TextureManager;
+
+pub fn piece_texture(piece: Piece, color: Color) -> &'static str {
+    match (piece, color) {
+        (Piece::Pawn, Color::White) => "white-pawn.png",
+        (Piece::Pawn, Color::Black) => "black-pawn.png",
+        (Piece::Knight, Color::White) => "white-knight.png",
+        (Piece::Knight, Color::Black) => "black-knight.png",
+        (Piece::Bishop, Color::White) => "white-bishop.png",
+        (Piece::Bishop, Color::Black) => "black-bishop.png",
+        (Piece::Rook, Color::White) => "white-rook.png",
+        (Piece::Rook, Color::Black) => "black-rook.png",
+        (Piece::Queen, Color::White) => "white-queen.png",
+        (Piece::Queen, Color::Black) => "black-queen.png",
+        (Piece::King, Color::White) => "white-king.png",
+        (Piece::King, Color::Black) => "black-king.png",
+    }
+}
+
+pub fn draw_piece(canvas: &mut Canvas<Window>, tex_man: &mut TextureManager<WindowContext>, assets: &str, piece: Piece, color: Color, dest: Rect) -> Result<(), String> {
+    let img_size = 128;
+    let texture = tex_man.load(&format!("{}/{}", assets, piece_texture(piece, color)))?;
+    let src = Rect::new(0,0,img_size,img_size);
+    let center = Point::new( 0,0);
+
+    canvas.copy_ex(
+        &texture, 
+        src,  
+        dest,
+        0.0,
+        center, 
+        false, 
+        false 
+    )
+}
+
+/// The squares, `highlighted` ones given as (file, rank), and the pieces on
+/// them. A `flipped` board is seen from Black's side.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_board(canvas: &mut Canvas<Window>, tex_man: &mut TextureManager<WindowContext>, assets: &str, square_size: u32, flipped: bool, highlighted: &[(i32, i32)], pieces: impl Iterator<Item = (Square, Piece, Color)>) -> Result<(), String> {
+    for i in 0..8 {
+        for j in 0..8 {
+            let x = i * square_size;
+            let y = j * square_size;
+            let coords = match flipped {
+                false => (i as i32, 7 - j as i32),
+                true => (7 - i as i32, j as i32),
+            };
+            
+            let color = if (i + j) % 2 == 0 && highlighted.contains(&coords) {
+                //Color::RGB(255, 150, 150)
+                pixels::Color::RGB(36, 158, 108)
+            } else if (i + j) % 2 == 1 && highlighted.contains(&coords) {
+                // Color::RGB(100, 70, 30) 
+                pixels::Color::RGB(38, 89, 68)
+            } else  if (i + j) % 2 == 0 {
+                // Color::RGB(255, 255, 255) 
+                pixels::Color::RGB(231,206,181)
+            } else {
+                // Color::RGB(20, 100, 20) 
+                pixels::Color::RGB(101,48,36)
+            };
+            canvas.set_draw_color(color);
+            canvas.fill_rect(sdl2::rect::Rect::new(x as i32, y as i32, square_size, square_size)).unwrap();
+        }
+    }
+
+    for (square, piece, color) in pieces {
+        let (file, rank) = (square.get_file().to_index() as u32, square.get_rank().to_index() as u32);
+        let (x, y) = match flipped {
+            false => (file * square_size, (7 - rank) * square_size),
+            true => ((7 - file) * square_size, rank * square_size),
+        };
+        let (x, y) = (x as i32, y as i32);
+        draw_piece(canvas, tex_man, assets, piece, color, Rect::new(x,y,square_size,square_size))?;
+    }
+    Ok(())
+}
+
+// Square under a click on the board
+pub fn square_at(x: i32, y: i32, square_size: u32, flipped: bool) -> Square {
+    let (file, rank) = (x / square_size as i32, 7 - y / square_size as i32);
+    let (file, rank) = match flipped {
+        false => (file, rank),
+        true => (7 - file, 7 - rank),
+    };
+    Square::make_square(chess::Rank::from_index(rank as usize), chess::File::from_index(file as usize))
+}
+
+// (file, rank) as used for `highlighted` in `draw_board`
+pub fn square_coords(square: Square) -> (i32, i32) {
+    (square.get_file().to_index() as i32, square.get_rank().to_index() as i32)
+}
+
+pub fn move_squares(m: ChessMove) -> Vec<(i32, i32)> {
+    vec![square_coords(m.get_source()), square_coords(m.get_dest())]
+}
+
+// A drop only highlights the square the piece lands on
+pub fn variant_move_squares(m: &shakmaty::Move) -> Vec<(i32, i32)> {
+    m.from().into_iter().chain([m.to()]).map(|square| square_coords(convert::square_from_shakmaty(square))).collect()
+}
+
+// The search thread notices the flag and its result goes nowhere
+pub fn cancel_search<T>(search_stop: &Arc<AtomicBool>, rx: &mut mpsc::Receiver<T>, calulation_running: &mut bool) {
+    search_stop.store(true, Ordering::Relaxed);
+    *rx = mpsc::channel().1;
+    *calulation_running = false;
+}
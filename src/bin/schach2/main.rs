@@ -0,0 +1,425 @@
+extern crate sdl2;
+
+use chess::Board;
+use chess::Square;
+use sdl2::mouse::MouseButton;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use std::time::SystemTime;
+use schach2::chess960;
+use schach2::chess960::Castling;
+use schach2::clock;
+use schach2::config::Config;
+use schach2::engine;
+use schach2::game::Game;
+use schach2::game::Player;
+use schach2::game_record;
+use schach2::puzzle;
+use schach2::skill;
+use schach2::tablebase;
+use schach2::time_manager;
+use schach2::trainer;
+use schach2::variant::Variant;
+mod texture_manager;
+mod board_view;
+mod panel;
+mod overlay;
+mod variant_mode;
+mod puzzle_mode;
+// use std::str::FromStr;
+
+/// How a mode with its own loop, variants or puzzles, was left.
+#[derive(PartialEq)]
+pub enum Exit {
+    Quit,
+    Standard,
+}
+
+fn main() -> Result<(), String> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (config, rest) = Config::from_args(&args)?;
+    config.init_global_thread_pool()?;
+    let square_size = config.gui.square_size;
+
+    let mut time_control_index = 0;
+    let mut skill = skill::Skill::full();
+    // New games start from a random Chess960 position
+    let mut chess960 = false;
+    let mut game = new_game(clock::PRESETS[time_control_index], skill, chess960);
+    // let mut game = Game::from_fen("4k3/1r6/3q4/8/8/8/5Q2/4K3 w - - 0 1", clock::PRESETS[time_control_index], [Player::Human, Player::Engine(skill)])?;
+
+    let sdl_context = sdl2::init().unwrap();
+    let video_subsystem = sdl_context.video().unwrap();
+    let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
+    let font = ttf_context.load_font(&config.gui.font, 16)?;
+
+    let window = video_subsystem.window("Schach", square_size * 8 + overlay::EVAL_BAR_WIDTH + panel::PANEL_WIDTH, square_size * 8)
+    .position_centered()
+    .build()
+    .unwrap();
+
+    let mut canvas = window.into_canvas().build().unwrap();
+
+    let texture_creator: sdl2::render::TextureCreator<sdl2::video::WindowContext> = canvas.texture_creator();
+    let mut tex_man: texture_manager::ResourceManager<'_, String, sdl2::render::Texture<'_>, sdl2::render::TextureCreator<sdl2::video::WindowContext>> = texture_manager::TextureManager::new(&texture_creator);
+
+    for color in ["black", "white"] {
+        for piece in ["bishop", "king", "queen", "knight", "pawn", "rook"] {
+            tex_man.load(&format!("{}/{}-{}.png", config.gui.assets, color, piece))?;
+        }
+    }
+
+    let mut panel = panel::Panel::new((square_size * 8 + overlay::EVAL_BAR_WIDTH) as i32, square_size * 8);
+    let mut event_pump = sdl_context.event_pump().unwrap();
+    let mut legal_moves: Vec<(i32,i32)> = Vec::new();
+    let mut active_piece: Option<Square> = None;
+
+    let mut calulation_running = false;
+    // A finished game is replaced by a new one after a few seconds
+    let mut game_over_since: Option<SystemTime> = None;
+    let mut rx: mpsc::Receiver<engine::SearchUpdate> = mpsc::channel().1;
+    let mut search_stop = Arc::new(AtomicBool::new(false));
+    let mut search_info: Option<engine::SearchInfo> = None;
+
+    // In analysis mode the engine searches every position for both sides without playing
+    let mut analysis = false;
+    let mut analysed: Option<Board> = None;
+    let mut multi_pv = 3;
+    // Short search for the human, only shown if the position didn't change in the meantime
+    let mut hint_rx: mpsc::Receiver<engine::SearchUpdate> = mpsc::channel().1;
+    let mut hint_stop = Arc::new(AtomicBool::new(false));
+    let mut hint_for: Option<Board> = None;
+    // Tablebase results of the moves of the selected piece, for the position and square they belong to
+    let tables = (!config.tablebase.path.is_empty()).then(|| tablebase::Tablebase::load(&config.tablebase.path));
+    let mut tablebase_for: Option<(Board, Square)> = None;
+    let mut tablebase_moves: Vec<(Square, tablebase::Wdl)> = Vec::new();
+    // Endgame training, the index of the material in `trainer::PRESETS` and the progress for a ply and position
+    let mut training: Option<(usize, trainer::Training)> = None;
+    let mut training_progress: Option<(usize, Board, trainer::Progress)> = None;
+    // Variants are played in their own loop in the same window
+    let mut play_variants = false;
+    // A puzzle file given on the command line starts with its puzzles
+    let mut puzzles = match rest.first() {
+        Some(path) => Some(puzzle::Session::new(puzzle::load(path)?)),
+        None => None,
+    };
+    let mut play_puzzles = puzzles.is_some();
+
+    'running: loop {
+        canvas.clear();
+        let brett = game.board();
+        let fifty_move_counter = game.fifty_move_counter();
+
+        if let Some(hinted) = hint_for {
+            if hinted != brett {
+                hint_stop.store(true, Ordering::Relaxed);
+                hint_rx = mpsc::channel().1;
+                hint_for = None;
+            } else if let Some(hint) = hint_rx.try_iter().find_map(|update| match update {
+                engine::SearchUpdate::BestMove(chess_move, _, _) => Some(chess_move),
+                engine::SearchUpdate::Info(_) | engine::SearchUpdate::NoMove => None,
+            }) {
+                legal_moves = board_view::move_squares(hint);
+                active_piece = None;
+                hint_for = None;
+            }
+        }
+
+        game.check_clock();
+        if game.is_over() {
+            if game_over_since.is_none() {
+                game_over_since = Some(SystemTime::now());
+                board_view::cancel_search(&search_stop, &mut rx, &mut calulation_running);
+            }
+            if !analysis && training.is_none() && game_over_since.is_some_and(|since| since.elapsed().unwrap().as_secs() > 10) {
+                legal_moves.clear();
+                game = new_game(clock::PRESETS[time_control_index], skill, chess960);
+            }
+        } else {
+            game_over_since = None;
+            let update = rx.try_recv();
+            if let Ok(engine::SearchUpdate::Info(info)) = update {
+                search_info = Some(info);
+            } else if let (true, Ok(engine::SearchUpdate::BestMove(_, _, info))) = (analysis, &update) {
+                // The search ran into its depth limit, keep showing its lines
+                calulation_running = false;
+                search_info = Some(info.clone());
+            } else if let Ok(engine::SearchUpdate::BestMove(chess_move, _, info)) = update {
+                calulation_running = false;
+                game.play(chess_move)?;
+                search_info = Some(info);
+                legal_moves = last_move_squares(game.record());
+            } else if let Ok(engine::SearchUpdate::NoMove) = update {
+                // Mate or stalemate, the game ends with the next check above
+                calulation_running = false;
+            } else if analysis && analysed != Some(brett) {
+                board_view::cancel_search(&search_stop, &mut rx, &mut calulation_running);
+                calulation_running = true;
+                search_info = None;
+                analysed = Some(brett);
+                (rx, search_stop) = start_search(brett, game.castling(), fifty_move_counter, time_manager::TimeManager::infinite(), multi_pv, skill::Skill::full(), &config);
+            } else if let (false, false, true, Player::Engine(engine_skill)) = (analysis, calulation_running, game.record().at_end(), game.player_to_move()) {
+                calulation_running = true;
+                search_info = None;
+                let clock = game.clock();
+                let color = brett.side_to_move();
+                let time_manager = if clock.is_unlimited() {
+                    time_manager::TimeManager::fixed(config.engine.move_time())
+                } else {
+                    time_manager::TimeManager::new(clock.remaining(color), clock.time_control().increment(), clock.moves_to_go(color), &brett)
+                };
+                (rx, search_stop) = start_search(brett, game.castling(), fifty_move_counter, time_manager, 1, engine_skill, &config);
+            }
+        }
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit {..} |
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                    break 'running;
+                },
+                Event::KeyDown { keycode: Some(keycode @ (Keycode::R | Keycode::T | Keycode::F)), .. } => {
+                    if keycode == Keycode::T {
+                        time_control_index = (time_control_index + 1) % clock::PRESETS.len();
+                    }
+                    if keycode == Keycode::F {
+                        chess960 = !chess960;
+                    }
+                    board_view::cancel_search(&search_stop, &mut rx, &mut calulation_running);
+                    analysed = None;
+                    search_info = None;
+                    training = None;
+                    game = new_game(clock::PRESETS[time_control_index], skill, chess960);
+                    if analysis {
+                        game.pause_clock();
+                    }
+                    legal_moves.clear();
+                    active_piece = None;
+                },
+                Event::KeyDown { keycode: Some(keycode @ (Keycode::Backspace | Keycode::Left | Keycode::Right | Keycode::Home | Keycode::End)), .. } => {
+                    board_view::cancel_search(&search_stop, &mut rx, &mut calulation_running);
+                    match keycode {
+                        Keycode::Backspace => { game.take_back(); },
+                        Keycode::Left => { game.undo(); },
+                        Keycode::Right => { game.redo(); },
+                        Keycode::Home => game.goto(0),
+                        _ => game.goto(game.record().len()),
+                    }
+                    legal_moves = last_move_squares(game.record());
+                    active_piece = None;
+                },
+                // Every press starts a position of the next material
+                Event::KeyDown { keycode: Some(Keycode::E), .. } => {
+                    let index = training.as_ref().map_or(0, |(index, _)| (index + 1) % trainer::PRESETS.len());
+                    let next = tables.as_ref().ok_or("No tablebase configured".to_string())
+                        .and_then(|tables| trainer::Training::new(trainer::Material::parse(trainer::PRESETS[index])?, tables));
+                    match next {
+                        Ok(next) => {
+                            board_view::cancel_search(&search_stop, &mut rx, &mut calulation_running);
+                            analysed = None;
+                            search_info = None;
+                            // Only the full engine defends with the tablebase
+                            game = Game::new(next.start(), clock::TimeControl::Unlimited, [Player::Human, Player::Engine(skill::Skill::full())]);
+                            if analysis {
+                                game.pause_clock();
+                            }
+                            training = Some((index, next));
+                            legal_moves.clear();
+                            active_piece = None;
+                        },
+                        Err(e) => eprintln!("{}", e),
+                    }
+                },
+                Event::KeyDown { keycode: Some(Keycode::V), .. } => {
+                    play_variants = true;
+                },
+                Event::KeyDown { keycode: Some(Keycode::P), .. } => {
+                    match puzzles.is_some() {
+                        true => play_puzzles = true,
+                        false => eprintln!("No puzzles, start with a .csv or .epd file as argument"),
+                    }
+                },
+                Event::KeyDown { keycode: Some(Keycode::A), .. } => {
+                    analysis = !analysis;
+                    board_view::cancel_search(&search_stop, &mut rx, &mut calulation_running);
+                    analysed = None;
+                    search_info = None;
+                    if analysis {
+                        game.pause_clock();
+                    } else {
+                        game.resume_clock();
+                    }
+                },
+                Event::KeyDown { keycode: Some(Keycode::H), .. } if !analysis && hint_for.is_none() && !game.is_over() && game.player_to_move() == Player::Human => {
+                    hint_for = Some(brett);
+                    (hint_rx, hint_stop) = start_search(brett, game.castling(), fifty_move_counter, time_manager::TimeManager::fixed(Duration::from_millis(500)), 1, skill::Skill::full(), &config);
+                },
+                Event::KeyDown { keycode: Some(keycode @ (Keycode::PageUp | Keycode::PageDown)), .. } if training.is_none() => {
+                    skill = match keycode {
+                        Keycode::PageUp => skill::Skill::new(skill.level() + 1),
+                        _ => skill::Skill::new(skill.level().saturating_sub(1)),
+                    };
+                    game.set_player(chess::Color::Black, Player::Engine(skill));
+                },
+                Event::KeyDown { keycode: Some(keycode @ (Keycode::Up | Keycode::Down)), .. } if analysis => {
+                    multi_pv = match keycode {
+                        Keycode::Up => (multi_pv + 1).min(5),
+                        _ => (multi_pv - 1).max(1),
+                    };
+                    analysed = None;
+                },
+                Event::MouseWheel { y, .. } => {
+                    panel.scroll(y);
+                },
+                // Clicks next to the board, on the eval bar or the panel
+                Event::MouseButtonDown { x, .. } if x >= (square_size * 8) as i32 => {},
+                Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => {
+                    let square = board_view::square_at(x, y, square_size, false);
+                    // Pawns always promote to a queen
+                    let chosen = active_piece.and_then(|from| game.legal_moves_from(from).into_iter()
+                        .find(|m| m.get_dest() == square && matches!(m.get_promotion(), None | Some(chess::Piece::Queen))));
+                    match chosen {
+                        Some(chess_move) => {
+                            board_view::cancel_search(&search_stop, &mut rx, &mut calulation_running);
+                            game.play(chess_move)?;
+                            legal_moves = board_view::move_squares(chess_move);
+                            active_piece = None;
+                        },
+                        None => {
+                            active_piece = Some(square);
+                            legal_moves = game.legal_moves_from(square).iter().map(|m| board_view::square_coords(m.get_dest())).collect();
+                        },
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        if play_variants || play_puzzles {
+            board_view::cancel_search(&search_stop, &mut rx, &mut calulation_running);
+            hint_stop.store(true, Ordering::Relaxed);
+            hint_for = None;
+            game.pause_clock();
+            let exit = match &mut puzzles {
+                Some(session) if play_puzzles => puzzle_mode::run(&mut canvas, &mut event_pump, &mut tex_man, &texture_creator, &font, &config, session)?,
+                _ => variant_mode::run(&mut canvas, &mut event_pump, &mut tex_man, &texture_creator, &font, &config, Variant::ALL[0])?,
+            };
+            (play_variants, play_puzzles) = (false, false);
+            if exit == Exit::Quit {
+                break 'running;
+            }
+            if !analysis {
+                game.resume_clock();
+            }
+            analysed = None;
+            legal_moves = last_move_squares(game.record());
+            active_piece = None;
+            continue;
+        }
+
+        let pieces = chess::ALL_SQUARES.into_iter().filter_map(|square| Some((square, brett.piece_on(square)?, brett.color_on(square)?)));
+        board_view::draw_board(&mut canvas, &mut tex_man, &config.gui.assets, square_size, false, &legal_moves, pieces)?;
+
+        if let (Some(tables), Some(from)) = (&tables, active_piece) {
+            if tablebase_for != Some((brett, from)) {
+                let legal = game.legal_moves_from(from);
+                // Syzygy tables know nothing about castling
+                let moves = match game.castling().is_empty() {
+                    true => tables.probe_moves(&brett, game.record().halfmove_clock()).unwrap_or_default(),
+                    false => Vec::new(),
+                };
+                tablebase_moves = moves.into_iter()
+                    .filter(|(m, _)| legal.contains(m) && matches!(m.get_promotion(), None | Some(chess::Piece::Queen)))
+                    .map(|(m, probe)| (m.get_dest(), probe.wdl))
+                    .collect();
+                tablebase_for = Some((brett, from));
+            }
+            overlay::draw_tablebase_moves(&mut canvas, &tablebase_moves, square_size)?;
+        }
+
+        if let Some(info) = &search_info {
+            if calulation_running {
+                overlay::draw_pv(&mut canvas, &info.pv, square_size)?;
+            }
+            overlay::draw_eval_bar(&mut canvas, (square_size * 8) as i32, square_size * 8, info.eval)?;
+        } else {
+            overlay::draw_eval_bar(&mut canvas, (square_size * 8) as i32, square_size * 8, 0.0)?;
+        }
+
+        let status = match game.result() {
+            Some((outcome, termination)) => format!("{}, {}", termination.name(), outcome.name()),
+            None if analysis => match brett.side_to_move() {
+                chess::Color::White => "Analysis, White to move".to_string(),
+                chess::Color::Black => "Analysis, Black to move".to_string(),
+            },
+            None if !game.record().at_end() => format!("Move {} of {}", game.record().ply(), game.record().len()),
+            None if calulation_running => "Black is thinking...".to_string(),
+            None if hint_for.is_some() => "Looking for a hint...".to_string(),
+            None => match brett.side_to_move() {
+                chess::Color::White => "White to move".to_string(),
+                chess::Color::Black => "Black to move".to_string(),
+            },
+        };
+        if let (Some(tables), Some((_, training))) = (&tables, &training) {
+            let ply = game.record().ply();
+            if !matches!(training_progress, Some((probed_ply, probed, _)) if probed_ply == ply && probed == brett) {
+                training_progress = Some((ply, brett, training.progress(tables, game.record())));
+            }
+        }
+        // The trainer reports while the human is to move and once the game is over
+        let status = match (&training, &training_progress) {
+            (Some((_, training)), Some((_, _, progress))) if game.record().at_end() && !calulation_running => training.report(progress),
+            _ => status,
+        };
+        panel.draw(&mut canvas, &texture_creator, &font, &panel::PanelContent { record: game.record(), clock: game.clock(), status: &status, info: search_info.as_ref(), analysis, skill })?;
+
+        canvas.present();
+        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 120));
+    }
+    
+    Ok(())
+}
+
+fn last_move_squares(record: &game_record::GameRecord) -> Vec<(i32, i32)> {
+    match record.last_move() {
+        Some(m) => board_view::move_squares(m),
+        None => Vec::new(),
+    }
+}
+
+// The engine plays Black. In Chess960 the king castles by moving onto its rook.
+fn new_game(time_control: clock::TimeControl, skill: skill::Skill, chess960: bool) -> Game {
+    let players = [Player::Human, Player::Engine(skill)];
+    if !chess960 {
+        return Game::new(Board::default(), time_control, players);
+    }
+    let (index, _, _) = chess960::random_start_position();
+    Game::chess960(index, time_control, players).unwrap()
+}
+
+fn start_search(brett: Board, castling: Castling, fifty_move_counter: u8, time_manager: time_manager::TimeManager, multi_pv: usize, skill: skill::Skill, config: &Config) -> (mpsc::Receiver<engine::SearchUpdate>, Arc<AtomicBool>) {
+    let (tx, rx) = mpsc::channel();
+    let mut engine = engine::Engine::new(brett);
+    engine.send_updates(tx.clone());
+    engine.set_multi_pv(multi_pv);
+    engine.set_skill(skill);
+    engine.set_tablebase(&config.tablebase.path);
+    engine.set_castling(castling);
+    let (depth, max_depth) = (config.engine.depth, config.engine.max_depth);
+    let search_stop = engine.stop_flag();
+    thread::spawn(move || {
+        let update = match engine.best_move(fifty_move_counter, depth, max_depth, time_manager) {
+            Some((chess_move, fifty, info)) => engine::SearchUpdate::BestMove(chess_move, fifty, info),
+            None => engine::SearchUpdate::NoMove,
+        };
+        // The receiver is gone if the search was cancelled
+        let _ = tx.send(update);
+    });
+    (rx, search_stop)
+}
@@ -0,0 +1,109 @@
+use chess::ChessMove;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::BlendMode;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use schach2::tablebase::Wdl;
+
+pub const EVAL_BAR_WIDTH: u32 = 24;
+
+// Moves of the side that searches and replies of the opponent get different colours
+const ARROW_COLORS: [(u8, u8, u8); 2] = [(30, 120, 220), (230, 130, 30)];
+const ARROW_ALPHA: [u8; 4] = [200, 150, 110, 80];
+const TABLEBASE_ALPHA: u8 = 130;
+
+/// Draws the first moves of a principal variation as arrows, fading out
+/// the further they are in the future.
+pub fn draw_pv(canvas: &mut Canvas<Window>, pv: &[ChessMove], square_size: u32) -> Result<(), String> {
+    canvas.set_blend_mode(BlendMode::Blend);
+    for (i, m) in pv.iter().take(ARROW_ALPHA.len()).enumerate().rev() {
+        let (r, g, b) = ARROW_COLORS[i % 2];
+        canvas.set_draw_color(Color::RGBA(r, g, b, ARROW_ALPHA[i]));
+        draw_arrow(canvas, square_center(m.get_source(), square_size), square_center(m.get_dest(), square_size), square_size as f32)?;
+    }
+    canvas.set_blend_mode(BlendMode::None);
+    Ok(())
+}
+
+/// Fills the destination squares of the selected piece by the tablebase
+/// result of the move for the side playing it.
+pub fn draw_tablebase_moves(canvas: &mut Canvas<Window>, moves: &[(chess::Square, Wdl)], square_size: u32) -> Result<(), String> {
+    canvas.set_blend_mode(BlendMode::Blend);
+    for (square, wdl) in moves {
+        let (r, g, b) = match wdl {
+            Wdl::Win => (40, 190, 70),
+            Wdl::CursedWin | Wdl::BlessedLoss => (220, 190, 40),
+            Wdl::Draw => (160, 160, 160),
+            Wdl::Loss => (210, 50, 50),
+        };
+        canvas.set_draw_color(Color::RGBA(r, g, b, TABLEBASE_ALPHA));
+        let x = square.get_file().to_index() as u32 * square_size;
+        let y = (7 - square.get_rank().to_index()) as u32 * square_size;
+        canvas.fill_rect(Rect::new(x as i32, y as i32, square_size, square_size))?;
+    }
+    canvas.set_blend_mode(BlendMode::None);
+    Ok(())
+}
+
+/// Vertical bar, the white part grows from the bottom with White's advantage.
+pub fn draw_eval_bar(canvas: &mut Canvas<Window>, x: i32, height: u32, eval: f32) -> Result<(), String> {
+    let white_share = 1.0 / (1.0 + 10f32.powf(-eval / 4.0));
+    let white_height = (height as f32 * white_share).round() as u32;
+    canvas.set_draw_color(Color::RGB(60, 60, 60));
+    canvas.fill_rect(Rect::new(x, 0, EVAL_BAR_WIDTH, height - white_height))?;
+    canvas.set_draw_color(Color::RGB(235, 235, 235));
+    canvas.fill_rect(Rect::new(x, (height - white_height) as i32, EVAL_BAR_WIDTH, white_height))?;
+    canvas.set_draw_color(Color::RGB(200, 60, 60));
+    canvas.fill_rect(Rect::new(x, height as i32 / 2, EVAL_BAR_WIDTH, 1))
+}
+
+fn square_center(square: chess::Square, square_size: u32) -> (f32, f32) {
+    let x = (square.get_file().to_index() as f32 + 0.5) * square_size as f32;
+    let y = (7.0 - square.get_rank().to_index() as f32 + 0.5) * square_size as f32;
+    (x, y)
+}
+
+fn draw_arrow(canvas: &mut Canvas<Window>, from: (f32, f32), to: (f32, f32), square_size: f32) -> Result<(), String> {
+    let length = ((to.0 - from.0).powi(2) + (to.1 - from.1).powi(2)).sqrt();
+    let direction = ((to.0 - from.0) / length, (to.1 - from.1) / length);
+    let normal = (-direction.1, direction.0);
+    let shaft = square_size * 0.09;
+    let head = square_size * 0.22;
+    let neck = (to.0 - direction.0 * square_size * 0.35, to.1 - direction.1 * square_size * 0.35);
+    let offset = |p: (f32, f32), w: f32| (p.0 + normal.0 * w, p.1 + normal.1 * w);
+    fill_polygon(canvas, &[
+        offset(from, shaft),
+        offset(neck, shaft),
+        offset(neck, head),
+        to,
+        offset(neck, -head),
+        offset(neck, -shaft),
+        offset(from, -shaft),
+    ])
+}
+
+// Scanline fill, every pixel is drawn once so translucent colours blend evenly
+fn fill_polygon(canvas: &mut Canvas<Window>, points: &[(f32, f32)]) -> Result<(), String> {
+    let top = points.iter().map(|p| p.1).fold(f32::INFINITY, f32::min).floor() as i32;
+    let bottom = points.iter().map(|p| p.1).fold(f32::NEG_INFINITY, f32::max).ceil() as i32;
+    let mut spans = Vec::new();
+    for y in top..=bottom {
+        let center = y as f32 + 0.5;
+        let mut crossings = Vec::new();
+        for (i, a) in points.iter().enumerate() {
+            let b = points[(i + 1) % points.len()];
+            if (a.1 <= center) != (b.1 <= center) {
+                crossings.push(a.0 + (center - a.1) / (b.1 - a.1) * (b.0 - a.0));
+            }
+        }
+        crossings.sort_by(|a, b| a.total_cmp(b));
+        for pair in crossings.chunks_exact(2) {
+            let (start, end) = (pair[0].round() as i32, pair[1].round() as i32);
+            if end > start {
+                spans.push(Rect::new(start, y, (end - start) as u32, 1));
+            }
+        }
+    }
+    canvas.fill_rects(&spans)
+}
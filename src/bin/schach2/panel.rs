@@ -0,0 +1,183 @@
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::render::TextureCreator;
+use sdl2::ttf::Font;
+use sdl2::video::Window;
+use sdl2::video::WindowContext;
+use schach2::chess960;
+use schach2::clock::Clock;
+use schach2::engine::SearchInfo;
+use schach2::game_record::GameRecord;
+use schach2::skill;
+use schach2::skill::Skill;
+
+pub const PANEL_WIDTH: u32 = 320;
+pub const MARGIN: i32 = 12;
+pub const LINE_HEIGHT: i32 = 24;
+// Longer lines don't fit into the panel
+const PV_MOVES: usize = 6;
+
+pub const BACKGROUND: Color = Color::RGB(40, 40, 40);
+pub const TEXT: Color = Color::RGB(230, 230, 230);
+pub const DIMMED: Color = Color::RGB(150, 150, 150);
+const HIGHLIGHT: Color = Color::RGB(38, 89, 68);
+
+/// Move list, clocks, evaluation and game status to the right of the board.
+pub struct Panel {
+    x: i32,
+    height: u32,
+    // First visible row of the move list
+    scroll: usize,
+    last_ply: Option<usize>,
+}
+
+pub struct PanelContent<'a> {
+    pub record: &'a GameRecord,
+    pub clock: &'a Clock,
+    pub status: &'a str,
+    pub info: Option<&'a SearchInfo>,
+    pub analysis: bool,
+    pub skill: Skill,
+}
+
+impl Panel {
+    pub fn new(x: i32, height: u32) -> Self {
+        Panel {
+            x,
+            height,
+            scroll: 0,
+            last_ply: None,
+        }
+    }
+
+    pub fn scroll(&mut self, rows: i32) {
+        self.scroll = (self.scroll as i32 - rows).max(0) as usize;
+    }
+
+    pub fn draw(&mut self, canvas: &mut Canvas<Window>, texture_creator: &TextureCreator<WindowContext>, font: &Font, content: &PanelContent) -> Result<(), String> {
+        canvas.set_draw_color(BACKGROUND);
+        canvas.fill_rect(Rect::new(self.x, 0, PANEL_WIDTH, self.height))?;
+
+        let x = self.x + MARGIN;
+        let mut y = MARGIN;
+        if !content.clock.is_unlimited() {
+            for (color, name) in [(chess::Color::Black, "Black"), (chess::Color::White, "White")] {
+                if content.clock.running() == Some(color) {
+                    canvas.set_draw_color(HIGHLIGHT);
+                    canvas.fill_rect(Rect::new(self.x, y - 2, PANEL_WIDTH, LINE_HEIGHT as u32))?;
+                }
+                draw_text(canvas, texture_creator, font, &format!("{}  {}", name, Clock::format(content.clock.remaining(color))), x, y, TEXT)?;
+                y += LINE_HEIGHT;
+            }
+            draw_text(canvas, texture_creator, font, &content.clock.time_control().name(), x, y, DIMMED)?;
+            y += LINE_HEIGHT + MARGIN;
+        }
+
+        draw_text(canvas, texture_creator, font, content.status, x, y, TEXT)?;
+        y += LINE_HEIGHT;
+        let strength = match content.skill.is_full() {
+            true => "Full strength".to_string(),
+            false => format!("Level {}/{} (~{} Elo)", content.skill.level(), skill::MAX_LEVEL, content.skill.elo()),
+        };
+        draw_text(canvas, texture_creator, font, &strength, x, y, DIMMED)?;
+        y += LINE_HEIGHT + MARGIN;
+
+        if let Some(info) = content.info {
+            draw_text(canvas, texture_creator, font, &format!("Eval: {}", format_eval(info.eval, info.tablebase)), x, y, TEXT)?;
+            y += LINE_HEIGHT;
+            if !info.tablebase {
+                draw_text(canvas, texture_creator, font, &format!("Depth: {}/{}", info.depth, info.max_depth), x, y, DIMMED)?;
+                y += LINE_HEIGHT;
+                let nps = info.stats.nodes as f64 / info.time.as_secs_f64().max(0.001);
+                draw_text(canvas, texture_creator, font, &format!("Nodes: {} ({:.0} kN/s)", info.stats.nodes, nps / 1000.0), x, y, DIMMED)?;
+                y += LINE_HEIGHT;
+            }
+            draw_text(canvas, texture_creator, font, &format!("Time: {:.2} s", info.time.as_secs_f64()), x, y, DIMMED)?;
+            y += LINE_HEIGHT;
+            y += MARGIN / 2;
+            if content.analysis {
+                for line in &info.lines {
+                    let text = format!("{}  {}", format_eval(line.eval, info.tablebase), chess960::san_line(&info.root, info.castling, &line.pv[..line.pv.len().min(PV_MOVES)]));
+                    draw_text(canvas, texture_creator, font, &text, x, y, TEXT)?;
+                    y += LINE_HEIGHT;
+                }
+            } else {
+                draw_text(canvas, texture_creator, font, &chess960::san_line(&info.root, info.castling, &info.pv[..info.pv.len().min(PV_MOVES)]), x, y, TEXT)?;
+                y += LINE_HEIGHT;
+            }
+        }
+        y += MARGIN;
+
+        canvas.set_draw_color(DIMMED);
+        canvas.draw_line((self.x + MARGIN, y), (self.x + PANEL_WIDTH as i32 - MARGIN, y))?;
+        y += MARGIN;
+        self.draw_moves(canvas, texture_creator, font, content.record, y)
+    }
+
+    fn draw_moves(&mut self, canvas: &mut Canvas<Window>, texture_creator: &TextureCreator<WindowContext>, font: &Font, record: &GameRecord, top: i32) -> Result<(), String> {
+        // A game starting with Black to move gets an empty white half move in the first row
+        let offset = match record.start().side_to_move() {
+            chess::Color::White => 0,
+            chess::Color::Black => 1,
+        };
+        let rows = (record.len() + offset).div_ceil(2);
+        let visible = ((self.height as i32 - top - MARGIN) / LINE_HEIGHT).max(1) as usize;
+
+        // Keep the current move in view whenever it changes
+        if self.last_ply != Some(record.ply()) {
+            self.last_ply = Some(record.ply());
+            let row = (record.ply() + offset).saturating_sub(1) / 2;
+            if row < self.scroll {
+                self.scroll = row;
+            } else if row >= self.scroll + visible {
+                self.scroll = row + 1 - visible;
+            }
+        }
+        self.scroll = self.scroll.min(rows.saturating_sub(visible));
+
+        let sans: Vec<&str> = record.san_moves().collect();
+        for row in self.scroll..rows.min(self.scroll + visible) {
+            let y = top + (row - self.scroll) as i32 * LINE_HEIGHT;
+            draw_text(canvas, texture_creator, font, &format!("{}.", row + 1), self.x + MARGIN, y, DIMMED)?;
+            for column in 0..2 {
+                let half_move = (row * 2 + column).checked_sub(offset);
+                let san = match half_move.and_then(|i| sans.get(i)) {
+                    Some(san) => *san,
+                    None if column == 0 && half_move.is_none() => "...",
+                    None => continue,
+                };
+                let x = self.x + MARGIN + 50 + column as i32 * 110;
+                if half_move.map(|i| i + 1) == Some(record.ply()) {
+                    canvas.set_draw_color(HIGHLIGHT);
+                    canvas.fill_rect(Rect::new(x - 4, y - 2, 100, LINE_HEIGHT as u32))?;
+                }
+                draw_text(canvas, texture_creator, font, san, x, y, TEXT)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn format_eval(eval: f32, tablebase: bool) -> String {
+    if tablebase {
+        return match eval {
+            e if e > 0.0 => "White wins (tablebase)".to_string(),
+            e if e < 0.0 => "Black wins (tablebase)".to_string(),
+            _ => "Draw (tablebase)".to_string(),
+        };
+    }
+    if eval.abs() >= 1000.0 {
+        return if eval > 0.0 { "+M".to_string() } else { "-M".to_string() };
+    }
+    format!("{:+.2}", eval)
+}
+
+pub fn draw_text(canvas: &mut Canvas<Window>, texture_creator: &TextureCreator<WindowContext>, font: &Font, text: &str, x: i32, y: i32, color: Color) -> Result<(), String> {
+    if text.is_empty() {
+        return Ok(());
+    }
+    let surface = font.render(text).blended(color).map_err(|e| e.to_string())?;
+    let texture = texture_creator.create_texture_from_surface(&surface).map_err(|e| e.to_string())?;
+    canvas.copy(&texture, None, Rect::new(x, y, surface.width(), surface.height()))
+}
@@ -0,0 +1,196 @@
+use chess::Board;
+use chess::ChessMove;
+use chess::MoveGen;
+use chess::Square;
+use sdl2::EventPump;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::render::TextureCreator;
+use sdl2::ttf::Font;
+use sdl2::video::Window;
+use sdl2::video::WindowContext;
+use std::time::Duration;
+use std::time::Instant;
+use schach2::config::Config;
+use schach2::notation;
+use schach2::puzzle::Puzzle;
+use schach2::puzzle::Session;
+use schach2::puzzle::Verdict;
+use crate::Exit;
+use crate::board_view;
+use crate::overlay;
+use crate::panel;
+use crate::texture_manager::TextureManager;
+
+// The opponent's replies wait a moment so they can be seen
+const REPLY_DELAY: Duration = Duration::from_millis(600);
+// A finished puzzle stays on the board before the next one comes
+const NEXT_DELAY: Duration = Duration::from_secs(2);
+
+// One puzzle on the board, `ply` counts the moves of the solution played so far
+struct Attempt {
+    number: usize,
+    puzzle: Puzzle,
+    brett: Board,
+    ply: usize,
+    reply: Option<(ChessMove, Instant)>,
+    finished: Option<Instant>,
+    status: String,
+    active_piece: Option<Square>,
+    highlighted: Vec<(i32, i32)>,
+}
+
+impl Attempt {
+    fn next(session: &mut Session) -> Self {
+        let (number, puzzle) = session.next_puzzle();
+        let puzzle = puzzle.clone();
+        let status = match puzzle.position().side_to_move() {
+            chess::Color::White => "Find the best move for White".to_string(),
+            chess::Color::Black => "Find the best move for Black".to_string(),
+        };
+        Attempt {
+            number,
+            brett: puzzle.start,
+            ply: 0,
+            // The opponent's move that sets up the puzzle is shown first
+            reply: puzzle.setup.map(|m| (m, Instant::now())),
+            finished: None,
+            status,
+            active_piece: None,
+            highlighted: Vec::new(),
+            puzzle,
+        }
+    }
+
+    // The solver's side is at the bottom
+    fn flipped(&self) -> bool {
+        self.puzzle.position().side_to_move() == chess::Color::Black
+    }
+}
+
+/// Puzzles from the session one after another, solved and failed ones are
+/// counted in its score. Left with P or by closing the window.
+#[allow(clippy::too_many_arguments)]
+pub fn run(canvas: &mut Canvas<Window>, event_pump: &mut EventPump, tex_man: &mut TextureManager<WindowContext>, texture_creator: &TextureCreator<WindowContext>, font: &Font, config: &Config, session: &mut Session) -> Result<Exit, String> {
+    let square_size = config.gui.square_size;
+    let mut attempt = Attempt::next(session);
+
+    loop {
+        canvas.clear();
+
+        if let Some((reply, since)) = attempt.reply {
+            if since.elapsed() >= REPLY_DELAY {
+                attempt.brett = attempt.brett.make_move_new(reply);
+                attempt.highlighted = board_view::move_squares(reply);
+                attempt.reply = None;
+            }
+        }
+        if attempt.finished.is_some_and(|since| since.elapsed() >= NEXT_DELAY) {
+            attempt = Attempt::next(session);
+        }
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit {..} |
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                    return Ok(Exit::Quit);
+                },
+                Event::KeyDown { keycode: Some(Keycode::P), .. } => {
+                    return Ok(Exit::Standard);
+                },
+                // Skipping an unfinished puzzle counts as failed
+                Event::KeyDown { keycode: Some(Keycode::N), .. } => {
+                    if attempt.finished.is_none() {
+                        session.score.record(false);
+                    }
+                    attempt = Attempt::next(session);
+                },
+                Event::MouseButtonDown { x, .. } if x >= (square_size * 8) as i32 => {},
+                Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } if attempt.reply.is_none() && attempt.finished.is_none() => {
+                    let square = board_view::square_at(x, y, square_size, attempt.flipped());
+                    let candidates: Vec<ChessMove> = match attempt.active_piece {
+                        Some(from) => MoveGen::new_legal(&attempt.brett).filter(|m| m.get_source() == from && m.get_dest() == square).collect(),
+                        None => Vec::new(),
+                    };
+                    // Pawns promote to a queen unless the solution wants something else
+                    let expected = attempt.puzzle.solution.get(attempt.ply).copied();
+                    let chosen = candidates.iter().copied().find(|m| Some(*m) == expected)
+                        .or(candidates.iter().copied().find(|m| matches!(m.get_promotion(), None | Some(chess::Piece::Queen))));
+                    match chosen {
+                        Some(chess_move) => {
+                            let before = attempt.brett;
+                            attempt.brett = before.make_move_new(chess_move);
+                            attempt.highlighted = board_view::move_squares(chess_move);
+                            attempt.active_piece = None;
+                            match attempt.puzzle.check(attempt.ply, &before, chess_move) {
+                                Verdict::Correct => {
+                                    attempt.reply = Some((attempt.puzzle.solution[attempt.ply + 1], Instant::now()));
+                                    attempt.ply += 2;
+                                    attempt.status = "Correct, keep going".to_string();
+                                },
+                                Verdict::Solved => {
+                                    session.score.record(true);
+                                    attempt.finished = Some(Instant::now());
+                                    attempt.status = "Solved!".to_string();
+                                },
+                                Verdict::Wrong => {
+                                    session.score.record(false);
+                                    attempt.finished = Some(Instant::now());
+                                    attempt.status = match expected {
+                                        Some(expected) => format!("Wrong, {} was the move", notation::san(expected, &before)),
+                                        None => "Wrong".to_string(),
+                                    };
+                                },
+                            }
+                        },
+                        None => {
+                            attempt.active_piece = Some(square);
+                            attempt.highlighted = MoveGen::new_legal(&attempt.brett)
+                                .filter(|m| m.get_source() == square)
+                                .map(|m| board_view::square_coords(m.get_dest()))
+                                .collect();
+                        },
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        let brett = attempt.brett;
+        let pieces = chess::ALL_SQUARES.into_iter().filter_map(|square| Some((square, brett.piece_on(square)?, brett.color_on(square)?)));
+        board_view::draw_board(canvas, tex_man, &config.gui.assets, square_size, attempt.flipped(), &attempt.highlighted, pieces)?;
+        draw_panel(canvas, texture_creator, font, config, session, &attempt)?;
+
+        canvas.present();
+        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 120));
+    }
+}
+
+fn draw_panel(canvas: &mut Canvas<Window>, texture_creator: &TextureCreator<WindowContext>, font: &Font, config: &Config, session: &Session, attempt: &Attempt) -> Result<(), String> {
+    let square_size = config.gui.square_size;
+    // No evaluation in puzzles, the bar stays empty
+    canvas.set_draw_color(panel::BACKGROUND);
+    canvas.fill_rect(Rect::new((square_size * 8) as i32, 0, overlay::EVAL_BAR_WIDTH + panel::PANEL_WIDTH, square_size * 8))?;
+
+    let x = (square_size * 8 + overlay::EVAL_BAR_WIDTH) as i32 + panel::MARGIN;
+    let score = session.score;
+    let mut lines = vec![(format!("Puzzle {}/{}  {}", attempt.number, session.len(), attempt.puzzle.id), panel::TEXT)];
+    if let Some(rating) = attempt.puzzle.rating {
+        lines.push((format!("Rating {}", rating), panel::DIMMED));
+    }
+    lines.push((attempt.status.clone(), panel::TEXT));
+    lines.push((String::new(), panel::TEXT));
+    lines.push((format!("Solved {}  Failed {}", score.solved, score.failed), panel::TEXT));
+    lines.push((format!("Streak {}  Best {}", score.streak, score.best_streak), panel::TEXT));
+    lines.push((String::new(), panel::TEXT));
+    lines.push(("N: next puzzle  P: back to the game".to_string(), panel::DIMMED));
+    for (i, (line, color)) in lines.iter().enumerate() {
+        if !line.is_empty() {
+            panel::draw_text(canvas, texture_creator, font, line, x, panel::MARGIN + i as i32 * panel::LINE_HEIGHT, *color)?;
+        }
+    }
+    Ok(())
+}
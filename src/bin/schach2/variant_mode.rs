@@ -0,0 +1,244 @@
+use sdl2::EventPump;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::render::TextureCreator;
+use sdl2::ttf::Font;
+use sdl2::video::Window;
+use sdl2::video::WindowContext;
+use shakmaty::Color;
+use shakmaty::Move;
+use shakmaty::Position;
+use shakmaty::Role;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use schach2::config::Config;
+use schach2::convert;
+use schach2::game::Player;
+use schach2::variant::Variant;
+use schach2::variant::VariantGame;
+use schach2::variant_engine;
+use schach2::variant_engine::VariantSearchInfo;
+use crate::Exit;
+use crate::board_view;
+use crate::overlay;
+use crate::panel;
+use crate::texture_manager::TextureManager;
+
+// Size of the pieces in the pocket rows of the panel
+const POCKET_SIZE: u32 = 44;
+// Full moves shown at the bottom of the panel
+const MOVE_ROWS: usize = 8;
+
+enum Selection {
+    Square(shakmaty::Square),
+    Drop(Role),
+}
+
+/// Plays variant games against the engine in the window of the standard
+/// game until the last variant is left with V or the window is closed.
+#[allow(clippy::too_many_arguments)]
+pub fn run(canvas: &mut Canvas<Window>, event_pump: &mut EventPump, tex_man: &mut TextureManager<WindowContext>, texture_creator: &TextureCreator<WindowContext>, font: &Font, config: &Config, mut variant: Variant) -> Result<Exit, String> {
+    let square_size = config.gui.square_size;
+    let panel_x = (square_size * 8 + overlay::EVAL_BAR_WIDTH) as i32;
+
+    let mut game = new_game(variant);
+    let mut selected: Option<Selection> = None;
+    let mut highlighted: Vec<(i32, i32)> = Vec::new();
+
+    let mut calulation_running = false;
+    let mut rx: mpsc::Receiver<(Move, VariantSearchInfo)> = mpsc::channel().1;
+    let mut search_stop = Arc::new(AtomicBool::new(false));
+    let mut search_info: Option<VariantSearchInfo> = None;
+
+    loop {
+        canvas.clear();
+
+        if let Ok((m, info)) = rx.try_recv() {
+            calulation_running = false;
+            game.play(&m)?;
+            highlighted = board_view::variant_move_squares(&m);
+            search_info = Some(info);
+        } else if !calulation_running && !game.is_over() && game.player_to_move() != Player::Human {
+            calulation_running = true;
+            (rx, search_stop) = start_search(game.position().clone(), config.engine.move_time());
+        }
+
+        for event in event_pump.poll_iter() {
+            match event {
+                Event::Quit {..} |
+                Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
+                    search_stop.store(true, Ordering::Relaxed);
+                    return Ok(Exit::Quit);
+                },
+                Event::KeyDown { keycode: Some(keycode @ (Keycode::V | Keycode::R)), .. } => {
+                    board_view::cancel_search(&search_stop, &mut rx, &mut calulation_running);
+                    if keycode == Keycode::V {
+                        // After the last variant it's back to standard chess
+                        match Variant::ALL.iter().skip_while(|v| **v != variant).nth(1) {
+                            Some(next) => variant = *next,
+                            None => return Ok(Exit::Standard),
+                        }
+                    }
+                    game = new_game(variant);
+                    search_info = None;
+                    selected = None;
+                    highlighted.clear();
+                },
+                Event::KeyDown { keycode: Some(Keycode::Backspace), .. } => {
+                    board_view::cancel_search(&search_stop, &mut rx, &mut calulation_running);
+                    game.take_back();
+                    selected = None;
+                    highlighted = game.last_move().map(board_view::variant_move_squares).unwrap_or_default();
+                },
+                // Pieces in White's pocket can be dropped by clicking them and then the square
+                Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } if x >= panel_x => {
+                    if let Some(role) = pocket_role_at(&game, panel_x, square_size * 8, x, y) {
+                        selected = Some(Selection::Drop(role));
+                        highlighted = game.drops(role).iter().map(|m| board_view::square_coords(convert::square_from_shakmaty(m.to()))).collect();
+                    }
+                },
+                Event::MouseButtonDown { x, .. } if x >= (square_size * 8) as i32 => {},
+                Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => {
+                    let file = shakmaty::File::new((x / square_size as i32) as u32);
+                    let rank = shakmaty::Rank::new((7 - y / square_size as i32) as u32);
+                    let square = shakmaty::Square::from_coords(file, rank);
+                    let candidates = match selected {
+                        Some(Selection::Square(from)) => game.legal_moves_from(from),
+                        Some(Selection::Drop(role)) => game.drops(role),
+                        None => Vec::new(),
+                    };
+                    // Pawns always promote to a queen
+                    let chosen = candidates.into_iter().find(|m| m.to() == square && matches!(m.promotion(), None | Some(Role::Queen)));
+                    match chosen {
+                        Some(m) => {
+                            board_view::cancel_search(&search_stop, &mut rx, &mut calulation_running);
+                            game.play(&m)?;
+                            highlighted = board_view::variant_move_squares(&m);
+                            selected = None;
+                        },
+                        None => {
+                            selected = Some(Selection::Square(square));
+                            highlighted = game.legal_moves_from(square).iter().map(|m| board_view::square_coords(convert::square_from_shakmaty(m.to()))).collect();
+                        },
+                    }
+                },
+                _ => {}
+            }
+        }
+
+        let board = game.position().board().clone();
+        let pieces = board.occupied().into_iter().filter_map(|square| {
+            let piece = board.piece_at(square)?;
+            Some((convert::square_from_shakmaty(square), convert::role_to_piece(piece.role), to_chess_color(piece.color)))
+        });
+        board_view::draw_board(canvas, tex_man, &config.gui.assets, square_size, false, &highlighted, pieces)?;
+        let eval = search_info.as_ref().map_or(0.0, |info| info.eval);
+        overlay::draw_eval_bar(canvas, (square_size * 8) as i32, square_size * 8, eval)?;
+
+        let status = match game.result() {
+            Some((outcome, termination)) => format!("{}, {}", termination.name(), outcome.name()),
+            None if calulation_running => "Black is thinking...".to_string(),
+            None => match game.position().turn() {
+                Color::White => "White to move".to_string(),
+                Color::Black => "Black to move".to_string(),
+            },
+        };
+        draw_panel(canvas, tex_man, texture_creator, font, config, &game, &status, search_info.as_ref())?;
+
+        canvas.present();
+        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 120));
+    }
+}
+
+fn new_game(variant: Variant) -> VariantGame {
+    VariantGame::new(variant, [Player::Human, Player::Engine(schach2::skill::Skill::full())])
+}
+
+#[allow(clippy::too_many_arguments)]
+fn draw_panel(canvas: &mut Canvas<Window>, tex_man: &mut TextureManager<WindowContext>, texture_creator: &TextureCreator<WindowContext>, font: &Font, config: &Config, game: &VariantGame, status: &str, info: Option<&VariantSearchInfo>) -> Result<(), String> {
+    let square_size = config.gui.square_size;
+    let x = (square_size * 8 + overlay::EVAL_BAR_WIDTH) as i32;
+    let height = square_size * 8;
+    canvas.set_draw_color(panel::BACKGROUND);
+    canvas.fill_rect(Rect::new(x, 0, panel::PANEL_WIDTH, height))?;
+
+    let left = x + panel::MARGIN;
+    let mut y = panel::MARGIN;
+    panel::draw_text(canvas, texture_creator, font, game.variant().name(), left, y, panel::TEXT)?;
+    y += panel::LINE_HEIGHT;
+    panel::draw_text(canvas, texture_creator, font, status, left, y, panel::TEXT)?;
+    y += panel::LINE_HEIGHT;
+    if let Some(info) = info {
+        let line = format!("{}  Depth {}  {} nodes", panel::format_eval(info.eval, false), info.depth, info.nodes);
+        panel::draw_text(canvas, texture_creator, font, &line, left, y, panel::DIMMED)?;
+    }
+    y += panel::LINE_HEIGHT;
+    if let (Some(white), Some(black)) = (game.remaining_checks(Color::White), game.remaining_checks(Color::Black)) {
+        let line = format!("Checks to give: White {}, Black {}", white, black);
+        panel::draw_text(canvas, texture_creator, font, &line, left, y, panel::TEXT)?;
+    }
+
+    for color in [Color::Black, Color::White] {
+        for (role, count, rect) in pocket_rects(game, x, height, color) {
+            board_view::draw_piece(canvas, tex_man, &config.gui.assets, convert::role_to_piece(role), to_chess_color(color), rect)?;
+            let text_color = if count == 0 { panel::DIMMED } else { panel::TEXT };
+            panel::draw_text(canvas, texture_creator, font, &count.to_string(), rect.right(), rect.bottom() - panel::LINE_HEIGHT, text_color)?;
+        }
+    }
+
+    // The last moves above White's pocket
+    let sans: Vec<&str> = game.san_moves().collect();
+    let rows: Vec<String> = sans.chunks(2).enumerate()
+        .map(|(i, pair)| format!("{}. {}", i + 1, pair.join(" ")))
+        .collect();
+    let mut y = height as i32 - 2 * POCKET_SIZE as i32 - panel::MARGIN - (MOVE_ROWS as i32 + 1) * panel::LINE_HEIGHT;
+    for row in &rows[rows.len().saturating_sub(MOVE_ROWS)..] {
+        panel::draw_text(canvas, texture_creator, font, row, left, y, panel::TEXT)?;
+        y += panel::LINE_HEIGHT;
+    }
+    Ok(())
+}
+
+// Crazyhouse pockets, Black's above White's at the bottom of the panel
+fn pocket_rects(game: &VariantGame, panel_x: i32, height: u32, color: Color) -> Vec<(Role, u8, Rect)> {
+    let row = match color {
+        Color::White => 1,
+        Color::Black => 2,
+    };
+    let y = height as i32 - panel::MARGIN - row * POCKET_SIZE as i32;
+    game.pocket(color).into_iter().enumerate()
+        .map(|(i, (role, count))| (role, count, Rect::new(panel_x + panel::MARGIN + i as i32 * (POCKET_SIZE as i32 + 16), y, POCKET_SIZE, POCKET_SIZE)))
+        .collect()
+}
+
+fn pocket_role_at(game: &VariantGame, panel_x: i32, height: u32, x: i32, y: i32) -> Option<Role> {
+    pocket_rects(game, panel_x, height, Color::White).into_iter()
+        .find(|(_, count, rect)| *count > 0 && rect.contains_point((x, y)))
+        .map(|(role, _, _)| role)
+}
+
+fn to_chess_color(color: Color) -> chess::Color {
+    match color {
+        Color::White => chess::Color::White,
+        Color::Black => chess::Color::Black,
+    }
+}
+
+fn start_search(pos: shakmaty::variant::VariantPosition, time: Duration) -> (mpsc::Receiver<(Move, VariantSearchInfo)>, Arc<AtomicBool>) {
+    let (tx, rx) = mpsc::channel();
+    let search_stop = Arc::new(AtomicBool::new(false));
+    let stop = search_stop.clone();
+    thread::spawn(move || {
+        let result = variant_engine::best_move(&pos, time, variant_engine::MAX_DEPTH, &stop);
+        // The receiver is gone if the search was cancelled
+        let _ = tx.send(result);
+    });
+    (rx, search_stop)
+}
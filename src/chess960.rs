@@ -0,0 +1,321 @@
+//! Chess960 on top of the chess crate, which only knows castling with the
+//! king on e1/e8 and the rooks in the corners. Boards of a Chess960 game carry
+//! no castling rights, those are kept as `Castling` next to the board and
+//! castling moves are played through shakmaty. Castling is written as the
+//! king moving onto its rook, just like `UCI_Chess960` does.
+
+use chess::BitBoard;
+use chess::Board;
+use chess::ChessMove;
+use chess::MoveGen;
+use chess::Piece;
+use rand::Rng;
+use shakmaty::Position;
+use std::str::FromStr;
+use crate::convert;
+use crate::notation;
+
+pub const POSITIONS: u16 = 960;
+/// Number of the standard start position
+pub const STANDARD: u16 = 518;
+
+// Squares of the two knights among the five left after bishops and queen
+const KNIGHTS: [(usize, usize); 10] = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)];
+
+/// Squares of the rooks that may still castle. Stays empty in standard
+/// games, whose boards keep their castling rights themselves.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Castling {
+    rooks: BitBoard,
+}
+
+impl Castling {
+    pub fn new(rooks: BitBoard) -> Self {
+        Castling { rooks }
+    }
+
+    pub fn none() -> Self {
+        Castling::default()
+    }
+
+    pub fn rooks(&self) -> BitBoard {
+        self.rooks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rooks == chess::EMPTY
+    }
+
+    // A moved or captured rook loses its right, a moved king both
+    fn after(self, brett: &Board, chess_move: ChessMove) -> Self {
+        let source = chess_move.get_source();
+        let mut rooks = self.rooks & !BitBoard::from_square(source) & !BitBoard::from_square(chess_move.get_dest());
+        if brett.piece_on(source) == Some(Piece::King) {
+            rooks &= !chess::get_rank(source.get_rank());
+        }
+        Castling { rooks }
+    }
+}
+
+/// Start position `index` from 0 to 959 in Scharnagl's numbering, every
+/// rook may castle. 518 is the standard start position.
+pub fn start_position(index: u16) -> Option<(Board, Castling)> {
+    if index >= POSITIONS {
+        return None;
+    }
+    let mut pieces = [None; 8];
+    let mut n = index as usize;
+    pieces[n % 4 * 2 + 1] = Some('b');
+    n /= 4;
+    pieces[n % 4 * 2] = Some('b');
+    n /= 4;
+    place(&mut pieces, n % 6, 'q');
+    n /= 6;
+    // The second knight first, so the square of the first one stays the same
+    let (first, second) = KNIGHTS[n];
+    place(&mut pieces, second, 'n');
+    place(&mut pieces, first, 'n');
+    for piece in ['r', 'k', 'r'] {
+        place(&mut pieces, 0, piece);
+    }
+
+    let backrank: String = pieces.iter().flatten().collect();
+    let fen = format!("{}/pppppppp/8/8/8/8/PPPPPPPP/{} w - - 0 1", backrank, backrank.to_uppercase());
+    let brett = Board::from_str(&fen).ok()?;
+    Some((brett, Castling::new(*brett.pieces(Piece::Rook))))
+}
+
+// Puts `piece` on the `nth` empty square of the back rank
+fn place(pieces: &mut [Option<char>; 8], nth: usize, piece: char) {
+    let file = (0..8).filter(|f| pieces[*f].is_none()).nth(nth).unwrap();
+    pieces[file] = Some(piece);
+}
+
+pub fn random_start_position() -> (u16, Board, Castling) {
+    let index = rand::thread_rng().gen_range(0..POSITIONS);
+    let (brett, castling) = start_position(index).unwrap();
+    (index, brett, castling)
+}
+
+/// Castling moves of the side to move, the king moves onto its rook.
+pub fn castling_moves(brett: &Board, castling: Castling) -> Vec<ChessMove> {
+    if castling.is_empty() {
+        return Vec::new();
+    }
+    match convert::to_shakmaty_chess960(brett, castling, 0) {
+        Some(pos) => pos.legal_moves().iter()
+            .filter(|m| m.is_castle())
+            .filter_map(convert::from_shakmaty_move_chess960)
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+pub fn is_castling(brett: &Board, castling: Castling, chess_move: ChessMove) -> bool {
+    castling.rooks() & BitBoard::from_square(chess_move.get_dest()) != chess::EMPTY
+        && brett.piece_on(chess_move.get_source()) == Some(Piece::King)
+        && brett.color_on(chess_move.get_dest()) == Some(brett.side_to_move())
+}
+
+pub fn legal_moves(brett: &Board, castling: Castling) -> Vec<ChessMove> {
+    let mut moves: Vec<ChessMove> = MoveGen::new_legal(brett).collect();
+    moves.extend(castling_moves(brett, castling));
+    moves
+}
+
+pub fn legal(brett: &Board, castling: Castling, chess_move: ChessMove) -> bool {
+    brett.legal(chess_move) || castling_moves(brett, castling).contains(&chess_move)
+}
+
+/// Position after the legal move `chess_move`, castling included.
+pub fn play(brett: &Board, castling: Castling, chess_move: ChessMove) -> (Board, Castling) {
+    if !is_castling(brett, castling, chess_move) {
+        return (brett.make_move_new(chess_move), castling.after(brett, chess_move));
+    }
+    let pos = convert::to_shakmaty_chess960(brett, castling, 0).expect("castling from a valid position");
+    let castle = convert::to_shakmaty_move(&pos, chess_move).expect("legal castling move");
+    convert::from_shakmaty_chess960(&pos.play(&castle).unwrap()).expect("position after castling")
+}
+
+// Castling puts the king onto its own rook, which is no capture
+pub fn is_zeroing(brett: &Board, chess_move: ChessMove) -> bool {
+    brett.piece_on(chess_move.get_source()) == Some(Piece::Pawn) || brett.color_on(chess_move.get_dest()) == Some(!brett.side_to_move())
+}
+
+/// Parses a move in UCI notation, castling as the king taking its own rook.
+pub fn from_uci(brett: &Board, castling: Castling, uci: &str) -> Option<ChessMove> {
+    ChessMove::from_str(uci).ok().filter(|m| legal(brett, castling, *m))
+}
+
+/// Like `notation::san`, castling is `O-O` or `O-O-O` by the side of the rook.
+pub fn san(brett: &Board, castling: Castling, chess_move: ChessMove) -> String {
+    if !is_castling(brett, castling, chess_move) {
+        return notation::san(chess_move, brett);
+    }
+    convert::to_shakmaty_chess960(brett, castling, 0)
+        .and_then(|pos| convert::to_shakmaty_move(&pos, chess_move).map(|m| shakmaty::san::SanPlus::from_move(pos, &m).to_string()))
+        .unwrap_or_else(|| chess_move.to_string())
+}
+
+pub fn parse_san(brett: &Board, castling: Castling, text: &str) -> Result<ChessMove, String> {
+    let kingside = match text.trim().trim_end_matches(['+', '#', '!', '?']).replace('0', "O").as_str() {
+        "O-O" => true,
+        "O-O-O" => false,
+        _ => return notation::parse_san(brett, text),
+    };
+    if castling.is_empty() {
+        return notation::parse_san(brett, text);
+    }
+    castling_moves(brett, castling).into_iter()
+        .find(|m| (m.get_dest().get_file() > m.get_source().get_file()) == kingside)
+        .ok_or(format!("Illegal move {} in {}", text, brett))
+}
+
+// Stops at the first move that isn't legal, like `notation::san_line`
+pub fn san_line(brett: &Board, castling: Castling, line: &[ChessMove]) -> String {
+    let (mut brett, mut castling) = (*brett, castling);
+    let mut sans = Vec::new();
+    for m in line {
+        if !legal(&brett, castling, *m) {
+            break;
+        }
+        sans.push(san(&brett, castling, *m));
+        (brett, castling) = play(&brett, castling, *m);
+    }
+    sans.join(" ")
+}
+
+/// Reads a FEN with castling rights in X-FEN (`KQkq`, or the file of the
+/// rook if it isn't the outermost one) or Shredder-FEN (`HAha`). Returns the
+/// board, its castling rights, the halfmove clock and the fullmove number.
+pub fn from_fen(fen: &str) -> Result<(Board, Castling, u32, u32), String> {
+    let parsed: shakmaty::fen::Fen = fen.parse().map_err(|e| format!("Invalid FEN {}: {}", fen, e))?;
+    let pos: shakmaty::Chess = parsed.into_position(shakmaty::CastlingMode::Chess960).map_err(|e| format!("Invalid FEN {}: {}", fen, e))?;
+    let (brett, castling) = convert::from_shakmaty_chess960(&pos).ok_or(format!("Invalid FEN {}", fen))?;
+    Ok((brett, castling, pos.halfmoves(), pos.fullmoves().get()))
+}
+
+pub fn x_fen(brett: &Board, castling: Castling, halfmoves: u32, fullmoves: u32) -> Option<String> {
+    let mut setup = convert::to_shakmaty_chess960(brett, castling, halfmoves)?.into_setup(shakmaty::EnPassantMode::Legal);
+    setup.fullmoves = std::num::NonZeroU32::new(fullmoves).unwrap_or(std::num::NonZeroU32::MIN);
+    Some(shakmaty::fen::Fen::from_setup(setup).to_string())
+}
+
+/// FEN with the files of all castling rooks, outer ones first.
+pub fn shredder_fen(brett: &Board, castling: Castling, halfmoves: u32, fullmoves: u32) -> Option<String> {
+    let fen = x_fen(brett, castling, halfmoves, fullmoves)?;
+    let mut rights = String::new();
+    for rank in [chess::Rank::First, chess::Rank::Eighth] {
+        let mut rooks: Vec<chess::Square> = (castling.rooks() & chess::get_rank(rank)).collect();
+        rooks.reverse();
+        for rook in rooks {
+            let file = (b'a' + rook.get_file().to_index() as u8) as char;
+            rights.push(if rank == chess::Rank::First { file.to_ascii_uppercase() } else { file });
+        }
+    }
+    if rights.is_empty() {
+        rights.push('-');
+    }
+    let mut fields: Vec<&str> = fen.split(' ').collect();
+    fields[2] = &rights;
+    Some(fields.join(" "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn uci(text: &str) -> ChessMove {
+        ChessMove::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn all_start_positions() {
+        let standard = Board::from_str("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1").unwrap();
+        assert_eq!(start_position(STANDARD).unwrap().0, standard);
+        assert_eq!(start_position(0).unwrap().0, Board::from_str("bbqnnrkr/pppppppp/8/8/8/8/PPPPPPPP/BBQNNRKR w - - 0 1").unwrap());
+        assert_eq!(start_position(POSITIONS), None);
+
+        let mut seen = HashSet::new();
+        for index in 0..POSITIONS {
+            let (brett, castling) = start_position(index).unwrap();
+            assert!(seen.insert(brett.get_hash()), "{}", index);
+            // One bishop on a light and one on a dark square
+            let bishops: Vec<usize> = (*brett.pieces(Piece::Bishop) & brett.color_combined(chess::Color::White)).map(|s| s.get_file().to_index()).collect();
+            assert_ne!(bishops[0] % 2, bishops[1] % 2, "{}", index);
+            let king = brett.king_square(chess::Color::White).get_file().to_index();
+            let rooks: Vec<usize> = (castling.rooks() & chess::get_rank(chess::Rank::First)).map(|s| s.get_file().to_index()).collect();
+            assert!(rooks.len() == 2 && rooks[0] < king && king < rooks[1], "{}", index);
+        }
+    }
+
+    #[test]
+    fn castling_on_both_sides() {
+        let (brett, castling, _, _) = from_fen("rk5r/8/8/8/8/8/8/RK5R w HAha - 0 1").unwrap();
+        let mut moves = castling_moves(&brett, castling);
+        moves.sort_by_key(|m| m.get_dest());
+        assert_eq!(moves, vec![uci("b1a1"), uci("b1h1")]);
+        assert_eq!(san(&brett, castling, uci("b1h1")), "O-O");
+        assert_eq!(parse_san(&brett, castling, "O-O-O"), Ok(uci("b1a1")));
+        assert!(!is_zeroing(&brett, uci("b1h1")));
+
+        let (after, rights) = play(&brett, castling, uci("b1h1"));
+        assert_eq!(after, Board::from_str("rk5r/8/8/8/8/8/8/R4RK1 b - - 0 1").unwrap());
+        assert_eq!(shredder_fen(&after, rights, 1, 1).unwrap(), "rk5r/8/8/8/8/8/8/R4RK1 b ha - 1 1");
+        assert_eq!(x_fen(&after, rights, 1, 1).unwrap(), "rk5r/8/8/8/8/8/8/R4RK1 b kq - 1 1");
+        assert_eq!(from_uci(&after, rights, "b8a8"), Some(uci("b8a8")));
+        assert_eq!(play(&after, rights, uci("h8h7")).1.rooks(), BitBoard::from_square(chess::Square::A8));
+    }
+
+    #[test]
+    fn king_staying_on_its_square() {
+        // The king is already on g1 and only the rook moves
+        let (brett, castling, _, _) = from_fen("4k3/8/8/8/8/8/8/R5KR w HA - 0 1").unwrap();
+        assert_eq!(san_line(&brett, castling, &[uci("g1h1"), uci("e8d8")]), "O-O Kd8");
+        assert_eq!(play(&brett, castling, uci("g1h1")).0, Board::from_str("4k3/8/8/8/8/8/8/R4RK1 b - - 0 1").unwrap());
+    }
+
+    fn perft(brett: &Board, castling: Castling, depth: u32) -> u64 {
+        if depth == 0 {
+            return 1;
+        }
+        legal_moves(brett, castling).into_iter()
+            .map(|m| {
+                let (brett, castling) = play(brett, castling, m);
+                perft(&brett, castling, depth - 1)
+            })
+            .sum()
+    }
+
+    #[test]
+    fn perft_with_castling() {
+        // Reference counts of the Chess960 perft suite
+        let cases = [
+            ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", [21, 528, 12189]),
+            ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", [21, 807, 18002]),
+            ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", [20, 479, 10471]),
+        ];
+        for (fen, counts) in cases {
+            let (brett, castling, _, _) = from_fen(fen).unwrap();
+            for (depth, count) in counts.into_iter().enumerate() {
+                assert_eq!(perft(&brett, castling, depth as u32 + 1), count, "{} depth {}", fen, depth + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn fen_formats() {
+        let x_fen_text = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let (brett, castling, _, _) = from_fen(x_fen_text).unwrap();
+        assert_eq!(castling, start_position(STANDARD).unwrap().1);
+        assert_eq!(x_fen(&brett, castling, 0, 1).unwrap(), x_fen_text);
+        assert_eq!(shredder_fen(&brett, castling, 0, 1).unwrap(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1");
+        // The inner of two rooks on the same side of the king needs its file in X-FEN
+        let (brett, castling, halfmoves, fullmoves) = from_fen("r1r1k3/8/8/8/8/8/8/R2K3R w Ac - 7 20").unwrap();
+        assert_eq!((halfmoves, fullmoves), (7, 20));
+        assert_eq!(x_fen(&brett, castling, halfmoves, fullmoves).unwrap(), "r1r1k3/8/8/8/8/8/8/R2K3R w Qc - 7 20");
+        assert_eq!(shredder_fen(&brett, castling, halfmoves, fullmoves).unwrap(), "r1r1k3/8/8/8/8/8/8/R2K3R w Ac - 7 20");
+        assert!(from_fen("8/8/8/8/8/8/8/8 w - - 0 1").is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use std::time::Duration;
+use std::time::Instant;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeControl {
+    Unlimited,
+    SuddenDeath { base: Duration },
+    Fischer { base: Duration, increment: Duration },
+    // Time used up to `delay` is given back after every move
+    Bronstein { base: Duration, delay: Duration },
+    // `session` is added again every time `moves` moves have been played, e.g. 40 moves in 90 minutes
+    MovesPerSession { moves: u32, session: Duration },
+}
+
+pub const PRESETS: [TimeControl; 6] = [
+    TimeControl::Unlimited,
+    TimeControl::SuddenDeath { base: Duration::from_secs(5 * 60) },
+    TimeControl::Fischer { base: Duration::from_secs(3 * 60), increment: Duration::from_secs(2) },
+    TimeControl::Fischer { base: Duration::from_secs(15 * 60), increment: Duration::from_secs(10) },
+    TimeControl::Bronstein { base: Duration::from_secs(5 * 60), delay: Duration::from_secs(3) },
+    TimeControl::MovesPerSession { moves: 40, session: Duration::from_secs(90 * 60) },
+];
+
+impl TimeControl {
+    pub fn moves_per_session(moves: u32, session: Duration) -> Result<Self, String> {
+        if moves == 0 {
+            return Err("A session needs at least one move".to_string());
+        }
+        Ok(TimeControl::MovesPerSession { moves, session })
+    }
+
+    pub fn base(&self) -> Option<Duration> {
+        match *self {
+            TimeControl::Unlimited => None,
+            TimeControl::SuddenDeath { base } |
+            TimeControl::Fischer { base, .. } |
+            TimeControl::Bronstein { base, .. } => Some(base),
+            TimeControl::MovesPerSession { session, .. } => Some(session),
+        }
+    }
+
+    // Time a player gets back per move when using at least that much
+    pub fn increment(&self) -> Duration {
+        match *self {
+            TimeControl::Fischer { increment, .. } => increment,
+            TimeControl::Bronstein { delay, .. } => delay,
+            _ => Duration::ZERO,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match *self {
+            TimeControl::Unlimited => "No clock".to_string(),
+            TimeControl::SuddenDeath { base } => format!("{} min", base.as_secs() / 60),
+            TimeControl::Fischer { base, increment } => format!("{}+{}", base.as_secs() / 60, increment.as_secs()),
+            TimeControl::Bronstein { base, delay } => format!("{} min, {} s Bronstein", base.as_secs() / 60, delay.as_secs()),
+            TimeControl::MovesPerSession { moves, session } => format!("{} moves / {} min", moves, session.as_secs() / 60),
+        }
+    }
+}
+
+pub struct Clock {
+    time_control: TimeControl,
+    remaining: [Duration; 2],
+    moves: [u32; 2],
+    running: Option<(chess::Color, Instant)>,
+}
+
+impl Clock {
+    pub fn new(time_control: TimeControl) -> Self {
+        let base = time_control.base().unwrap_or(Duration::ZERO);
+        Clock {
+            time_control,
+            remaining: [base; 2],
+            moves: [0; 2],
+            running: None,
+        }
+    }
+
+    pub fn time_control(&self) -> TimeControl {
+        self.time_control
+    }
+
+    pub fn is_unlimited(&self) -> bool {
+        self.time_control == TimeControl::Unlimited
+    }
+
+    pub fn running(&self) -> Option<chess::Color> {
+        self.running.map(|(color, _)| color)
+    }
+
+    pub fn remaining(&self, color: chess::Color) -> Duration {
+        let stored = self.remaining[color.to_index()];
+        match self.running {
+            Some((running, since)) if running == color => stored.saturating_sub(since.elapsed()),
+            _ => stored,
+        }
+    }
+
+    pub fn flagged(&self) -> Option<chess::Color> {
+        if self.is_unlimited() {
+            return None;
+        }
+        chess::ALL_COLORS.into_iter().find(|&color| self.remaining(color).is_zero())
+    }
+
+    // Moves the side still has to play until the next session starts
+    pub fn moves_to_go(&self, color: chess::Color) -> Option<u32> {
+        match self.time_control {
+            TimeControl::MovesPerSession { moves, .. } => self.moves[color.to_index()].checked_rem(moves).map(|played| moves - played),
+            _ => None,
+        }
+    }
+
+    // Completes the move of `mover`: charges the time used, adds increment
+    // or delay and starts the opponent's clock.
+    pub fn press(&mut self, mover: chess::Color) {
+        if let Some((color, since)) = self.running {
+            if color == mover {
+                let used = since.elapsed();
+                let i = mover.to_index();
+                self.remaining[i] = self.remaining[i].saturating_sub(used);
+                if !self.remaining[i].is_zero() {
+                    self.moves[i] += 1;
+                    match self.time_control {
+                        TimeControl::Fischer { increment, .. } => self.remaining[i] += increment,
+                        TimeControl::Bronstein { delay, .. } => self.remaining[i] += used.min(delay),
+                        TimeControl::MovesPerSession { moves, session } if self.moves[i].is_multiple_of(moves) => self.remaining[i] += session,
+                        _ => (),
+                    }
+                }
+            }
+        }
+        self.running = Some((!mover, Instant::now()));
+    }
+
+    // Lets the clock of `color` run without finishing a move, used after take-backs
+    pub fn start(&mut self, color: chess::Color) {
+        self.stop();
+        self.running = Some((color, Instant::now()));
+    }
+
+    pub fn stop(&mut self) {
+        if let Some((color, since)) = self.running.take() {
+            let i = color.to_index();
+            self.remaining[i] = self.remaining[i].saturating_sub(since.elapsed());
+        }
+    }
+
+    pub fn format(duration: Duration) -> String {
+        let secs = duration.as_secs();
+        if secs < 20 {
+            format!("{}:{:02}.{}", secs / 60, secs % 60, duration.subsec_millis() / 100)
+        } else {
+            format!("{}:{:02}", secs / 60, secs % 60)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chess::Color;
+    use std::thread;
+
+    // The clock runs in real time, a few milliseconds pass between the calls
+    fn close_to(actual: Duration, expected: Duration) -> bool {
+        actual <= expected && expected - actual < Duration::from_millis(200)
+    }
+
+    #[test]
+    fn increment_and_delay() {
+        let mut clock = Clock::new(TimeControl::Fischer { base: Duration::from_secs(60), increment: Duration::from_secs(2) });
+        clock.start(Color::White);
+        clock.press(Color::White);
+        assert!(close_to(clock.remaining(Color::White), Duration::from_secs(62)));
+        assert_eq!(clock.running(), Some(Color::Black));
+
+        // Bronstein gives back the time used, but never more than the delay
+        let mut clock = Clock::new(TimeControl::Bronstein { base: Duration::from_secs(60), delay: Duration::from_secs(3) });
+        clock.start(Color::White);
+        thread::sleep(Duration::from_millis(50));
+        clock.press(Color::White);
+        assert!(close_to(clock.remaining(Color::White), Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn session_rollover() {
+        assert!(TimeControl::moves_per_session(0, Duration::from_secs(60)).is_err());
+        let mut clock = Clock::new(TimeControl::moves_per_session(2, Duration::from_secs(60)).unwrap());
+        assert_eq!(clock.moves_to_go(Color::White), Some(2));
+        for moves_to_go in [1, 2, 1] {
+            clock.start(Color::White);
+            clock.press(Color::White);
+            assert_eq!(clock.moves_to_go(Color::White), Some(moves_to_go));
+        }
+        assert!(close_to(clock.remaining(Color::White), Duration::from_secs(120)));
+        assert_eq!(clock.moves_to_go(Color::Black), Some(2));
+    }
+
+    #[test]
+    fn flag_fall() {
+        let mut clock = Clock::new(TimeControl::Fischer { base: Duration::from_millis(30), increment: Duration::from_secs(2) });
+        assert_eq!(clock.flagged(), None);
+        clock.start(Color::Black);
+        thread::sleep(Duration::from_millis(60));
+        assert_eq!(clock.flagged(), Some(Color::Black));
+        // No increment for a move made after the flag fell
+        clock.press(Color::Black);
+        assert_eq!(clock.remaining(Color::Black), Duration::ZERO);
+
+        let mut clock = Clock::new(TimeControl::Unlimited);
+        clock.start(Color::White);
+        assert_eq!(clock.flagged(), None);
+    }
+}
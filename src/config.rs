@@ -0,0 +1,186 @@
+use serde::Deserialize;
+use std::path::Path;
+use std::time::Duration;
+use crate::tablebase;
+
+/// Read from the working directory if it exists and no other file is given.
+pub const DEFAULT_FILE: &str = "schach2.toml";
+
+/// Settings of all binaries. Every key can be left out of the file and
+/// overridden on the command line, e.g. `--engine.threads=8`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub engine: EngineConfig,
+    pub gui: GuiConfig,
+    pub tablebase: TablebaseConfig,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineConfig {
+    pub threads: usize,
+    // First iteration of the iterative deepening, with captures and checks followed up to `max_depth`
+    pub depth: u64,
+    pub max_depth: u64,
+    // Time per move without a clock, in milliseconds
+    pub move_time: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct GuiConfig {
+    pub assets: String,
+    pub font: String,
+    pub square_size: u32,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TablebaseConfig {
+    // Directory with Syzygy tables, empty to play without tablebase
+    pub path: String,
+}
+
+impl Default for EngineConfig {
+    fn default() -> Self {
+        EngineConfig { threads: 18, depth: 4, max_depth: 10, move_time: 125 }
+    }
+}
+
+impl Default for GuiConfig {
+    fn default() -> Self {
+        GuiConfig { assets: "img".to_string(), font: "fonts/DejaVuSans.ttf".to_string(), square_size: 100 }
+    }
+}
+
+impl Default for TablebaseConfig {
+    fn default() -> Self {
+        TablebaseConfig { path: tablebase::DIRECTORY.to_string() }
+    }
+}
+
+impl EngineConfig {
+    pub fn move_time(&self) -> Duration {
+        Duration::from_millis(self.move_time)
+    }
+}
+
+impl Config {
+    /// Takes `--config <file>` and `--<section>.<key>=<value>` out of `args`
+    /// and returns the validated config with the remaining arguments.
+    pub fn from_args(args: &[String]) -> Result<(Config, Vec<String>), String> {
+        let mut file = None;
+        let mut overrides = Vec::new();
+        let mut rest = Vec::new();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.strip_prefix("--") {
+                Some("config") => file = Some(args.next().ok_or("--config needs a file")?.clone()),
+                Some(setting) if setting.contains('.') => {
+                    let (key, value) = setting.split_once('=').ok_or(format!("Expected --{}=<value>", setting))?;
+                    overrides.push((key.to_string(), value.to_string()));
+                },
+                _ => rest.push(arg.clone()),
+            }
+        }
+
+        let (path, required) = match file {
+            Some(path) => (path, true),
+            None => (DEFAULT_FILE.to_string(), false),
+        };
+        let mut table = match std::fs::read_to_string(&path) {
+            Ok(text) => text.parse::<toml::Table>().map_err(|e| format!("{}: {}", path, e))?,
+            Err(e) if required => return Err(format!("{}: {}", path, e)),
+            Err(_) => toml::Table::new(),
+        };
+        for (key, value) in overrides {
+            set(&mut table, &key, &value)?;
+        }
+        let explicit_tablebase = table.get("tablebase").and_then(|section| section.get("path")).is_some();
+        let mut config: Config = table.try_into().map_err(|e| format!("Invalid config: {}", e))?;
+        // Only a path that was asked for has to exist, without the default folder there is no tablebase
+        if !explicit_tablebase && !Path::new(&config.tablebase.path).is_dir() {
+            config.tablebase.path = String::new();
+        }
+        config.validate()?;
+        Ok((config, rest))
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let engine = &self.engine;
+        if engine.threads == 0 || engine.threads > 256 {
+            return Err(format!("engine.threads must be between 1 and 256, not {}", engine.threads));
+        }
+        if engine.depth < 2 || engine.depth > 50 {
+            return Err(format!("engine.depth must be between 2 and 50, not {}", engine.depth));
+        }
+        if engine.max_depth < engine.depth || engine.max_depth > 50 {
+            return Err(format!("engine.max_depth must be between engine.depth ({}) and 50, not {}", engine.depth, engine.max_depth));
+        }
+        if engine.move_time == 0 {
+            return Err("engine.move_time must be at least 1 ms".to_string());
+        }
+        if self.gui.square_size < 20 || self.gui.square_size > 400 {
+            return Err(format!("gui.square_size must be between 20 and 400, not {}", self.gui.square_size));
+        }
+        if !self.tablebase.path.is_empty() && !Path::new(&self.tablebase.path).is_dir() {
+            return Err(format!("tablebase.path: {} is not a directory, use \"\" to play without tablebase", self.tablebase.path));
+        }
+        Ok(())
+    }
+
+    pub fn thread_pool(&self) -> Result<rayon::ThreadPool, String> {
+        rayon::ThreadPoolBuilder::new().num_threads(self.engine.threads).build().map_err(|e| e.to_string())
+    }
+
+    pub fn init_global_thread_pool(&self) -> Result<(), String> {
+        rayon::ThreadPoolBuilder::new().num_threads(self.engine.threads).build_global().map_err(|e| e.to_string())
+    }
+}
+
+// The value is read as TOML, anything that isn't valid TOML is taken as a string
+fn set(table: &mut toml::Table, key: &str, value: &str) -> Result<(), String> {
+    let value = match format!("value = {}", value).parse::<toml::Table>() {
+        Ok(mut parsed) => parsed.remove("value").unwrap(),
+        Err(_) => toml::Value::String(value.to_string()),
+    };
+    let (section, name) = key.split_once('.').ok_or(format!("Expected <section>.<key>, not {}", key))?;
+    match table.entry(section).or_insert_with(|| toml::Value::Table(toml::Table::new())) {
+        toml::Value::Table(section) => {
+            section.insert(name.to_string(), value);
+            Ok(())
+        },
+        _ => Err(format!("{} is not a section", section)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn defaults_and_overrides() {
+        let (config, rest) = Config::from_args(&args(&["bench", "--engine.threads=2", "--tablebase.path=", "3", "--gui.assets=pieces"])).unwrap();
+        assert_eq!(rest, args(&["bench", "3"]));
+        assert_eq!(config.engine.threads, 2);
+        assert_eq!(config.engine.depth, 4);
+        assert_eq!(config.tablebase.path, "");
+        assert_eq!(config.gui.assets, "pieces");
+    }
+
+    #[test]
+    fn invalid_settings_are_reported() {
+        let error = |a: &[&str]| Config::from_args(&args(a)).unwrap_err();
+        assert!(error(&["--engine.threads=0"]).contains("engine.threads"));
+        assert!(error(&["--engine.depth=6", "--engine.max_depth=4"]).contains("engine.max_depth"));
+        assert!(error(&["--engine.threads=many"]).contains("threads"));
+        assert!(error(&["--engine.thraeds=4"]).contains("thraeds"));
+        assert!(error(&["--tablebase.path=/does/not/exist"]).contains("tablebase.path"));
+        assert!(error(&["--config", "/does/not/exist.toml"]).contains("exist.toml"));
+    }
+}
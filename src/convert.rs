@@ -0,0 +1,252 @@
+//! Conversions between the chess crate, shakmaty, UCI and SAN. The engine
+//! works on `chess::Board`, tablebase and notation go through shakmaty.
+
+use chess::Board;
+use chess::ChessMove;
+use shakmaty::FromSetup;
+use shakmaty::Position;
+use std::num::NonZeroU32;
+use std::str::FromStr;
+use crate::chess960::Castling;
+use crate::notation;
+
+// Both crates number the squares from a1 = 0 to h8 = 63
+pub fn square_to_shakmaty(square: chess::Square) -> shakmaty::Square {
+    shakmaty::Square::new(square.to_index() as u32)
+}
+
+pub fn square_from_shakmaty(square: shakmaty::Square) -> chess::Square {
+    chess::ALL_SQUARES[square as usize]
+}
+
+pub fn piece_to_role(piece: chess::Piece) -> shakmaty::Role {
+    match piece {
+        chess::Piece::Pawn => shakmaty::Role::Pawn,
+        chess::Piece::Knight => shakmaty::Role::Knight,
+        chess::Piece::Bishop => shakmaty::Role::Bishop,
+        chess::Piece::Rook => shakmaty::Role::Rook,
+        chess::Piece::Queen => shakmaty::Role::Queen,
+        chess::Piece::King => shakmaty::Role::King,
+    }
+}
+
+pub fn role_to_piece(role: shakmaty::Role) -> chess::Piece {
+    match role {
+        shakmaty::Role::Pawn => chess::Piece::Pawn,
+        shakmaty::Role::Knight => chess::Piece::Knight,
+        shakmaty::Role::Bishop => chess::Piece::Bishop,
+        shakmaty::Role::Rook => chess::Piece::Rook,
+        shakmaty::Role::Queen => chess::Piece::Queen,
+        shakmaty::Role::King => chess::Piece::King,
+    }
+}
+
+fn color_to_shakmaty(color: chess::Color) -> shakmaty::Color {
+    match color {
+        chess::Color::White => shakmaty::Color::White,
+        chess::Color::Black => shakmaty::Color::Black,
+    }
+}
+
+/// `brett` as a shakmaty position. The chess crate has no move counters, so
+/// the halfmove clock is passed in and the move number starts at 1.
+pub fn to_shakmaty(brett: &Board, halfmoves: u32) -> Option<shakmaty::Chess> {
+    // shakmaty wants the squares of the rooks that may still castle
+    let mut castling_rights = shakmaty::Bitboard::EMPTY;
+    for (color, rank) in [(chess::Color::White, shakmaty::Rank::First), (chess::Color::Black, shakmaty::Rank::Eighth)] {
+        let rights = brett.castle_rights(color);
+        if rights.has_kingside() {
+            castling_rights.add(shakmaty::Square::from_coords(shakmaty::File::H, rank));
+        }
+        if rights.has_queenside() {
+            castling_rights.add(shakmaty::Square::from_coords(shakmaty::File::A, rank));
+        }
+    }
+    shakmaty::Chess::from_setup(setup(brett, castling_rights, halfmoves), shakmaty::CastlingMode::Standard).ok()
+}
+
+/// A board of a Chess960 game as a shakmaty position, the castling rights
+/// are kept next to the board since the chess crate can't hold them.
+pub fn to_shakmaty_chess960(brett: &Board, castling: Castling, halfmoves: u32) -> Option<shakmaty::Chess> {
+    let setup = setup(brett, shakmaty::Bitboard::from(castling.rooks().0), halfmoves);
+    shakmaty::Chess::from_setup(setup, shakmaty::CastlingMode::Chess960).ok()
+}
+
+fn setup(brett: &Board, castling_rights: shakmaty::Bitboard, halfmoves: u32) -> shakmaty::Setup {
+    let bitboard = |bb: chess::BitBoard| shakmaty::Bitboard::from(bb.0);
+    let board = shakmaty::Board::from_bitboards(
+        shakmaty::ByRole {
+            pawn: bitboard(*brett.pieces(chess::Piece::Pawn)),
+            knight: bitboard(*brett.pieces(chess::Piece::Knight)),
+            bishop: bitboard(*brett.pieces(chess::Piece::Bishop)),
+            rook: bitboard(*brett.pieces(chess::Piece::Rook)),
+            queen: bitboard(*brett.pieces(chess::Piece::Queen)),
+            king: bitboard(*brett.pieces(chess::Piece::King)),
+        },
+        shakmaty::ByColor {
+            white: bitboard(*brett.color_combined(chess::Color::White)),
+            black: bitboard(*brett.color_combined(chess::Color::Black)),
+        },
+    );
+
+    // The chess crate stores the square of the pawn that can be taken, shakmaty the square behind it
+    let ep_square = brett.en_passant().map(|sq| match brett.side_to_move() {
+        chess::Color::White => square_to_shakmaty(sq.uup()),
+        chess::Color::Black => square_to_shakmaty(sq.udown()),
+    });
+
+    shakmaty::Setup {
+        board,
+        promoted: shakmaty::Bitboard::EMPTY,
+        pockets: None,
+        turn: color_to_shakmaty(brett.side_to_move()),
+        castling_rights,
+        ep_square,
+        remaining_checks: None,
+        halfmoves,
+        fullmoves: NonZeroU32::MIN,
+    }
+}
+
+pub fn from_shakmaty(pos: &shakmaty::Chess) -> Option<Board> {
+    let fen = shakmaty::fen::Fen::from_position(pos.clone(), shakmaty::EnPassantMode::Legal);
+    Board::from_str(&fen.to_string()).ok()
+}
+
+/// The board without castling rights and the castling rights on their own.
+pub fn from_shakmaty_chess960(pos: &shakmaty::Chess) -> Option<(Board, Castling)> {
+    let mut setup = pos.clone().into_setup(shakmaty::EnPassantMode::Legal);
+    let castling = Castling::new(chess::BitBoard(u64::from(setup.castling_rights)));
+    setup.castling_rights = shakmaty::Bitboard::EMPTY;
+    let brett = Board::from_str(&shakmaty::fen::Fen::from_setup(setup).to_string()).ok()?;
+    Some((brett, castling))
+}
+
+/// The legal shakmaty move for `chess_move`, `None` if it isn't legal in `pos`.
+pub fn to_shakmaty_move(pos: &shakmaty::Chess, chess_move: ChessMove) -> Option<shakmaty::Move> {
+    shakmaty::uci::Uci::from_ascii(chess_move.to_string().as_bytes()).ok()?.to_move(pos).ok()
+}
+
+pub fn from_shakmaty_move(m: &shakmaty::Move) -> Option<ChessMove> {
+    match *m {
+        shakmaty::Move::Normal { from, to, promotion, .. } => {
+            Some(ChessMove::new(square_from_shakmaty(from), square_from_shakmaty(to), promotion.map(role_to_piece)))
+        },
+        shakmaty::Move::EnPassant { from, to } => {
+            Some(ChessMove::new(square_from_shakmaty(from), square_from_shakmaty(to), None))
+        },
+        // shakmaty moves the king onto the rook, the chess crate two squares to the side
+        shakmaty::Move::Castle { king, rook } => {
+            let from = square_from_shakmaty(king);
+            let file = if rook.file() > king.file() { chess::File::G } else { chess::File::C };
+            Some(ChessMove::new(from, chess::Square::make_square(from.get_rank(), file), None))
+        },
+        shakmaty::Move::Put { .. } => None,
+    }
+}
+
+// In Chess960 the king moves onto its rook, as in UCI_Chess960
+pub fn from_shakmaty_move_chess960(m: &shakmaty::Move) -> Option<ChessMove> {
+    match *m {
+        shakmaty::Move::Castle { king, rook } => Some(ChessMove::new(square_from_shakmaty(king), square_from_shakmaty(rook), None)),
+        _ => from_shakmaty_move(m),
+    }
+}
+
+pub fn to_uci(chess_move: ChessMove) -> String {
+    chess_move.to_string()
+}
+
+/// Parses a move in UCI notation, e.g. `e1g1` or `e7e8q`, if it is legal in `brett`.
+pub fn from_uci(brett: &Board, uci: &str) -> Option<ChessMove> {
+    ChessMove::from_str(uci).ok().filter(|m| brett.legal(*m))
+}
+
+/// Standard algebraic notation of a legal move, with check and mate markers.
+pub fn to_san(brett: &Board, chess_move: ChessMove) -> Option<String> {
+    brett.legal(chess_move).then(|| notation::san(chess_move, brett))
+}
+
+pub fn from_san(brett: &Board, san: &str) -> Option<ChessMove> {
+    notation::parse_san(brett, san).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Castling on both sides, en passant, promotions and pins
+    const POSITIONS: [&str; 6] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        "8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1",
+        "r3k2r/8/8/8/8/8/8/R3K2R b Kq - 0 1",
+    ];
+
+    fn boards() -> Vec<Board> {
+        POSITIONS.iter().map(|fen| Board::from_str(fen).unwrap()).collect()
+    }
+
+    #[test]
+    fn squares_and_pieces() {
+        for square in chess::ALL_SQUARES {
+            let converted = square_to_shakmaty(square);
+            assert_eq!(converted.to_string(), square.to_string());
+            assert_eq!(square_from_shakmaty(converted), square);
+        }
+        for piece in chess::ALL_PIECES {
+            assert_eq!(role_to_piece(piece_to_role(piece)), piece);
+        }
+    }
+
+    #[test]
+    fn boards_round_trip() {
+        for brett in boards() {
+            let pos = to_shakmaty(&brett, 0).unwrap();
+            assert_eq!(from_shakmaty(&pos), Some(brett), "{}", brett);
+            assert_eq!(pos.legal_moves().len(), chess::MoveGen::new_legal(&brett).len(), "{}", brett);
+        }
+    }
+
+    #[test]
+    fn moves_round_trip() {
+        for brett in boards() {
+            let pos = to_shakmaty(&brett, 0).unwrap();
+            for m in chess::MoveGen::new_legal(&brett) {
+                let converted = to_shakmaty_move(&pos, m).unwrap_or_else(|| panic!("{} in {}", m, brett));
+                assert_eq!(from_shakmaty_move(&converted), Some(m));
+                assert_eq!(from_uci(&brett, &to_uci(m)), Some(m));
+                let san = to_san(&brett, m).unwrap();
+                assert_eq!(from_san(&brett, &san), Some(m), "{} in {}", san, brett);
+            }
+        }
+    }
+
+    #[test]
+    fn castling_and_en_passant() {
+        let brett = Board::from_str(POSITIONS[1]).unwrap();
+        let pos = to_shakmaty(&brett, 0).unwrap();
+        let castle = to_shakmaty_move(&pos, ChessMove::from_str("e1g1").unwrap()).unwrap();
+        assert!(matches!(castle, shakmaty::Move::Castle { king: shakmaty::Square::E1, rook: shakmaty::Square::H1 }));
+        assert_eq!(to_san(&brett, ChessMove::from_str("e1c1").unwrap()).as_deref(), Some("O-O-O"));
+        assert_eq!(from_san(&brett, "O-O"), ChessMove::from_str("e1g1").ok());
+
+        let brett = Board::from_str(POSITIONS[3]).unwrap();
+        let pos = to_shakmaty(&brett, 0).unwrap();
+        assert_eq!(pos.ep_square(shakmaty::EnPassantMode::Legal), Some(shakmaty::Square::F6));
+        let ep = to_shakmaty_move(&pos, ChessMove::from_str("e5f6").unwrap()).unwrap();
+        assert!(matches!(ep, shakmaty::Move::EnPassant { .. }));
+        assert_eq!(to_san(&brett, ChessMove::from_str("e5f6").unwrap()).as_deref(), Some("exf6"));
+    }
+
+    #[test]
+    fn illegal_moves_are_rejected() {
+        let brett = Board::default();
+        assert_eq!(from_uci(&brett, "e2e5"), None);
+        assert_eq!(from_uci(&brett, "nonsense"), None);
+        assert_eq!(from_san(&brett, "Ke2"), None);
+        assert_eq!(to_shakmaty_move(&to_shakmaty(&brett, 0).unwrap(), ChessMove::from_str("e1g1").unwrap()), None);
+    }
+}
@@ -3,34 +3,187 @@ use chess::ChessMove;
 use chess::BoardStatus;
 use std::collections::HashMap;
 use std::time::Duration;
-use std::time::SystemTime;
+use std::time::Instant;
+use std::sync::mpsc;
+use std::thread;
 use rayon::prelude::*;
+use rand::Rng;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
 use rand::seq::SliceRandom;
-use rand::thread_rng;
-use std::num::NonZeroU32;
-use shakmaty::FromSetup;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use crate::time_manager::TimeManager;
+use crate::chess960;
+use crate::chess960::Castling;
+use crate::skill::Skill;
+use crate::search_stats::SearchStats;
+use crate::tablebase;
+use crate::tablebase::Tablebase;
 
 
+#[derive(Clone, Debug)]
+pub struct SearchInfo {
+    // In pawns from White's point of view
+    pub eval: f32,
+    pub depth: u64,
+    pub max_depth: u64,
+    pub stats: SearchStats,
+    // Nodes of this iteration divided by the nodes of the one before
+    pub branching_factor: Option<f64>,
+    pub time: Duration,
+    pub tablebase: bool,
+    // Position the lines start from
+    pub root: chess::Board,
+    pub castling: Castling,
+    pub pv: Vec<ChessMove>,
+    // The best root moves, as many as set with `set_multi_pv`
+    pub lines: Vec<Line>,
+}
+
+impl SearchInfo {
+    pub fn pv_san(&self) -> String {
+        chess960::san_line(&self.root, self.castling, &self.pv)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Line {
+    // In pawns from White's point of view
+    pub eval: f32,
+    pub pv: Vec<ChessMove>,
+}
+
+pub enum SearchUpdate {
+    // Sent after every finished iteration
+    Info(SearchInfo),
+    BestMove(ChessMove, u8, SearchInfo),
+    // Mate or stalemate, there is no move to play
+    NoMove,
+}
+
+// State of the search below one root move
+pub struct SearchContext<'a> {
+    eval_map: HashMap<u64, f32>,
+    abort: &'a AtomicBool,
+    // Nominal depth of the iteration, deeper nodes are extensions
+    depth: u64,
+    stats: SearchStats,
+    // Triangular PV table, `pv[ply]` is the best line found from that ply on
+    pv: Vec<Vec<ChessMove>>,
+}
+
+impl SearchContext<'_> {
+    fn clear_pv(&mut self, ply: usize) {
+        if self.pv.len() < ply + 2 {
+            self.pv.resize(ply + 2, Vec::new());
+        }
+        self.pv[ply].clear();
+    }
+
+    // `m` is the new best move at `ply`, followed by the line of its subtree
+    fn update_pv(&mut self, ply: usize, m: ChessMove) {
+        let (upper, lower) = self.pv.split_at_mut(ply + 1);
+        upper[ply].clear();
+        upper[ply].push(m);
+        upper[ply].extend_from_slice(&lower[0]);
+    }
+}
+
 pub struct Engine {
     brett: chess::Board,
+    // Chess960 castling is only searched at the root, `minmax` works on the
+    // chess crate's moves and never sees castling that crate can't represent
+    castling: Castling,
+    stop: Arc<AtomicBool>,
+    updates: Option<mpsc::Sender<SearchUpdate>>,
+    multi_pv: usize,
+    skill: Skill,
+    depth_limit: Option<u64>,
+    random: bool,
+    // Empty if no tablebase should be used
+    tablebase: String,
 }
 
 impl Engine {
     pub fn new(brett: chess::Board) -> Self {
         Engine {
             brett,
+            castling: Castling::none(),
+            stop: Arc::new(AtomicBool::new(false)),
+            updates: None,
+            multi_pv: 1,
+            skill: Skill::full(),
+            depth_limit: None,
+            random: true,
+            tablebase: tablebase::DIRECTORY.to_string(),
+        }
+    }
+
+    pub fn set_multi_pv(&mut self, multi_pv: usize) {
+        self.multi_pv = multi_pv.max(1);
+    }
+
+    pub fn set_skill(&mut self, skill: Skill) {
+        self.skill = skill;
+    }
+
+    // No iteration deeper than `depth` is started, like UCI `go depth`
+    pub fn set_depth_limit(&mut self, depth: u64) {
+        self.depth_limit = Some(depth);
+    }
+
+    // Without randomness the same position is always searched the same way, needed for `bench`
+    pub fn set_random(&mut self, random: bool) {
+        self.random = random;
+    }
+
+    pub fn set_tablebase(&mut self, path: &str) {
+        self.tablebase = path.to_string();
+    }
+
+    /// Chess960 castling rights kept apart from the board. Castling is played
+    /// at the root but not seen deeper in the search.
+    pub fn set_castling(&mut self, castling: Castling) {
+        self.castling = castling;
+    }
+
+    // Upper bounds for `depth` and `max_depth` from the skill level and the depth limit
+    fn depth_limit(&self) -> Option<(u64, u64)> {
+        match (self.skill.depth_limit(), self.depth_limit) {
+            (Some((depth, max_depth)), Some(limit)) => Some((depth.min(limit), max_depth)),
+            (Some(limits), None) => Some(limits),
+            (None, Some(limit)) => Some((limit, u64::MAX)),
+            (None, None) => None,
         }
     }
 
-    pub fn minmax(brett: &chess::Board, fifty_move_counter: u8, depth:u64, max_depth: u64, mut alpha: f32, mut beta: f32, maximizing_player: bool, eval_map: &mut HashMap<u64, f32>, depth_counter: u64, tables: &shakmaty_syzygy::Tablebase<shakmaty::Chess>) -> f32 { 
-        if fifty_move_counter > 50 {
+    pub fn send_updates(&mut self, updates: mpsc::Sender<SearchUpdate>) {
+        self.updates = Some(updates);
+    }
+
+    // Setting the flag aborts a running search, its result is meaningless afterwards
+    pub fn stop_flag(&self) -> Arc<AtomicBool> {
+        self.stop.clone()
+    }
+
+    pub fn minmax(brett: &chess::Board, fifty_move_counter: u8, depth:u64, max_depth: u64, mut alpha: f32, mut beta: f32, maximizing_player: bool, ctx: &mut SearchContext, depth_counter: u64) -> f32 { 
+        ctx.stats.nodes += 1;
+        // depth_counter is one more than the distance to the root
+        if depth_counter - 1 > ctx.depth {
+            ctx.stats.qnodes += 1;
+        }
+        ctx.stats.seldepth = ctx.stats.seldepth.max(depth_counter - 1);
+        ctx.clear_pv(depth_counter as usize);
+        if fifty_move_counter >= 100 || ctx.abort.load(Ordering::Relaxed) {
             return 0.0;
         }
         match brett.status() { 
             BoardStatus::Ongoing => () , 
             BoardStatus::Stalemate => {
                 let eval = 0.0;   
-                eval_map.insert(brett.get_hash(), eval);
+                ctx.eval_map.insert(brett.get_hash(), eval);
                 return eval;
             }
             BoardStatus::Checkmate =>  {
@@ -42,21 +195,21 @@ impl Engine {
                 return eval;
             }
         }
-        if eval_map.contains_key(&brett.get_hash()) {
-            return eval_map[&brett.get_hash()];
+        ctx.stats.tt_probes += 1;
+        if let Some(eval) = ctx.eval_map.get(&brett.get_hash()) {
+            ctx.stats.tt_hits += 1;
+            return *eval;
         }
         if depth == 0 || depth_counter >= max_depth {
             let eval = Engine::eval_board(brett, fifty_move_counter);   
             return eval;
         }
         
-        let mut moves = MoveGen::new_legal(brett).collect::<Vec<chess::ChessMove>>();
-        let mut rng = thread_rng();
-        moves.shuffle(&mut rng);
+        let moves = MoveGen::new_legal(brett).collect::<Vec<chess::ChessMove>>();
 
         if maximizing_player {
             let mut max_eval = f32::NEG_INFINITY;
-            for m in moves {
+            for (i, m) in moves.into_iter().enumerate() {
                 let fifty = if brett.piece_on(m.get_source()) == Some(chess::Piece::Pawn) || brett.piece_on(m.get_dest()) != None {
                     0
                 } else {
@@ -65,32 +218,39 @@ impl Engine {
                 let eval = if depth == 1 {
                     if brett.piece_on(m.get_dest()) != None {
                         let brett = brett.make_move_new(m);
-                        Engine::minmax( &brett, fifty, 2, max_depth ,alpha, beta, false,eval_map, depth_counter + 1, tables)
+                        Engine::minmax( &brett, fifty, 2, max_depth ,alpha, beta, false,ctx, depth_counter + 1)
                     } else {
                         let brett = brett.make_move_new(m);
                         if brett.checkers().0 != 0 {
-                            Engine::minmax( &brett, fifty, 2, max_depth ,alpha, beta, false,eval_map, depth_counter + 1, tables)
+                            Engine::minmax( &brett, fifty, 2, max_depth ,alpha, beta, false,ctx, depth_counter + 1)
                         } else {
-                            Engine::minmax( &brett, fifty, depth - 1, max_depth ,alpha, beta, false,eval_map, depth_counter + 1, tables)
+                            Engine::minmax( &brett, fifty, depth - 1, max_depth ,alpha, beta, false,ctx, depth_counter + 1)
                         }
                     }
                 } else {
                     let brett = brett.make_move_new(m);
-                    Engine::minmax( &brett, fifty, depth - 1, max_depth ,alpha, beta, false,eval_map, depth_counter + 1, tables)
+                    Engine::minmax( &brett, fifty, depth - 1, max_depth ,alpha, beta, false,ctx, depth_counter + 1)
                 };                
                 if eval < 1000.0 && eval > -1000.0 {
-                    eval_map.insert(brett.get_hash(), eval);
+                    ctx.eval_map.insert(brett.get_hash(), eval);
                 }   
+                if eval > max_eval {
+                    ctx.update_pv(depth_counter as usize, m);
+                }
                 max_eval = max_eval.max(eval);
                 alpha = alpha.max(eval);
                 if beta <= alpha {
+                    ctx.stats.cutoffs += 1;
+                    if i == 0 {
+                        ctx.stats.first_move_cutoffs += 1;
+                    }
                     break;
                 }
             }   
             return max_eval;
         } else {
             let mut min_eval = f32::INFINITY;
-            for m in moves {
+            for (i, m) in moves.into_iter().enumerate() {
                 let fifty = if brett.piece_on(m.get_source()) == Some(chess::Piece::Pawn) || brett.piece_on(m.get_dest()) != None {
                     0
                 } else {
@@ -99,25 +259,32 @@ impl Engine {
                 let eval = if depth == 1 {
                     if brett.piece_on(m.get_dest()) != None{
                         let brett = brett.make_move_new(m);
-                        Engine::minmax( &brett, fifty, 2, max_depth ,alpha, beta, true,eval_map, depth_counter + 1, tables)
+                        Engine::minmax( &brett, fifty, 2, max_depth ,alpha, beta, true,ctx, depth_counter + 1)
                     } else {
                         let brett = brett.make_move_new(m);
                         if brett.checkers().0 != 0 {
-                            Engine::minmax( &brett, fifty, 2, max_depth ,alpha, beta, true,eval_map, depth_counter + 1, tables)
+                            Engine::minmax( &brett, fifty, 2, max_depth ,alpha, beta, true,ctx, depth_counter + 1)
                         } else {
-                            Engine::minmax( &brett, fifty, depth - 1, max_depth ,alpha, beta, true,eval_map, depth_counter + 1, tables)
+                            Engine::minmax( &brett, fifty, depth - 1, max_depth ,alpha, beta, true,ctx, depth_counter + 1)
                         }
                     }
                 } else {
                     let brett = brett.make_move_new(m);
-                    Engine::minmax( &brett, fifty, depth - 1, max_depth ,alpha, beta, true,eval_map, depth_counter + 1, tables)
+                    Engine::minmax( &brett, fifty, depth - 1, max_depth ,alpha, beta, true,ctx, depth_counter + 1)
                 };  
                 if eval < 1000.0 && eval > -1000.0 {
-                    eval_map.insert(brett.get_hash(), eval);
+                    ctx.eval_map.insert(brett.get_hash(), eval);
                 }              
+                if eval < min_eval {
+                    ctx.update_pv(depth_counter as usize, m);
+                }
                 min_eval = min_eval.min(eval);
                 beta = beta.min(eval);
                 if beta <= alpha {
+                    ctx.stats.cutoffs += 1;
+                    if i == 0 {
+                        ctx.stats.first_move_cutoffs += 1;
+                    }
                     break;
                 }
             }
@@ -125,76 +292,197 @@ impl Engine {
         } 
     }   
 
-    pub fn best_move(&self, mut fifty_move_counter: u8, depth: u64, max_depth: u64, time: u32, start: SystemTime) -> (ChessMove, u8) {
-        let mut tables: shakmaty_syzygy::Tablebase<shakmaty::Chess> = shakmaty_syzygy::Tablebase::new();
-        match tables.add_directory("3-4-5") {
-            Ok(_) => (),
-            Err(_) => println!("Tablebase not loaded"),
+    /// The move to play, the halfmove clock after it and what the search
+    /// found. `None` if the game is already over by mate or stalemate.
+    pub fn best_move(&self, mut fifty_move_counter: u8, mut depth: u64, mut max_depth: u64, mut time_manager: TimeManager) -> Option<(ChessMove, u8, SearchInfo)> {
+        // The tablebase plays perfectly, a weakened engine has to find its own way.
+        // Syzygy tables know nothing about castling.
+        let table = match self.skill.is_full() && !self.tablebase.is_empty() && self.castling.is_empty() {
+            true => Tablebase::load(&self.tablebase).best_move(&self.brett, fifty_move_counter as u32),
+            false => None,
+        };
+        if let Some((table_move, eval)) = table {
+            let lines = vec![Line { eval, pv: vec![table_move] }];
+            let info = SearchInfo { eval, depth: 0, max_depth: 0, stats: SearchStats::default(), branching_factor: None, time: time_manager.elapsed(), tablebase: true, root: self.brett, castling: self.castling, pv: vec![table_move], lines };
+            return Some((table_move, fifty_move_counter+1, info));
         }
-        let table_move = Engine::best_move_table(&self.brett, fifty_move_counter as u32, &tables);
-        if  table_move != None {
-            println!("Tablebase move found");
-            return (table_move.unwrap().0, fifty_move_counter+1);
+
+        // The first of equally good moves is played, so their order decides between them
+        let mut order = chess960::legal_moves(&self.brett, self.castling);
+        if order.is_empty() {
+            return None;
         }
-        let mut best = f32::MIN;
-        
-        let maximizing_player = match self.brett.side_to_move() {
-            chess::Color::Black => true,
-            chess::Color::White => false,
+        let mut rng = match self.random {
+            true => StdRng::from_entropy(),
+            false => StdRng::seed_from_u64(0),
         };
-        let factor = match self.brett.side_to_move() {
-            chess::Color::Black => -1.0,
-            chess::Color::White =>  1.0,
-        };
-        
-        let all_moves = chess::MoveGen::new_legal(&self.brett).collect::<Vec<chess::ChessMove>>();
-        let mut best_move = all_moves[0];
-        let mut moves: Vec<(f32, chess::ChessMove)> = Vec::new();
-        all_moves.par_iter()
-        .map(|m| {
-            let fifty = if self.brett.piece_on(m.get_source()) == Some(chess::Piece::Pawn) || self.brett.piece_on(m.get_dest()) != None  {
-                0
-            } else {
-                fifty_move_counter + 1
-            };
-            let brett = self.brett.make_move_new(*m);
-            let mut eval_map:HashMap<u64, f32>  = HashMap::new();
-            let eval = match self.brett.status() { 
-                BoardStatus::Stalemate => 0.0,
-                BoardStatus::Checkmate => 100000.0,
-                BoardStatus::Ongoing => {
-                    factor * Engine::minmax(&brett, fifty, depth - 1, max_depth, f32::NEG_INFINITY, f32::INFINITY, maximizing_player, &mut eval_map, 2, &tables)
-                },
-            };
-            (eval,*m)
-        }).collect_into_vec(&mut moves);
-        let mut rng = thread_rng();
-        moves.shuffle(&mut rng);
-        
-        for (eval,m) in moves {
-            if eval > best {
-                best = eval;
-                best_move = m;
-            }
+        if self.random {
+            order.shuffle(&mut rng);
         }
+        if let Some((depth_limit, max_depth_limit)) = self.depth_limit() {
+            depth = depth.min(depth_limit);
+            max_depth = max_depth.min(max_depth_limit);
+        }
+        // A weakened engine needs exact scores of the near-best moves to choose from
+        let lines = self.multi_pv.max(self.skill.candidates());
 
-        if SystemTime::now() < start + Duration::new(0,time) && depth < 50 {
-            if max_depth <= depth + 6 {
-                return self.best_move(fifty_move_counter, depth, max_depth+2, time,start);
+        // Set by the watchdog when the hard limit is reached or the search is stopped from outside
+        let abort = AtomicBool::new(false);
+        let hard_deadline = time_manager.hard_deadline();
+        let mut result: Option<Vec<(f32, Vec<ChessMove>)>> = None;
+        let mut shallow: Option<Vec<(f32, Vec<ChessMove>)>> = None;
+        let mut completed = (0, 0);
+        let mut stats = SearchStats::default();
+        let mut previous_total = 0;
+        let mut previous_iteration: Option<u64> = None;
+        let mut branching_factor = None;
+        thread::scope(|s| {
+            let (done_tx, done_rx) = mpsc::channel::<()>();
+            let abort = &abort;
+            s.spawn(move || {
+                while let Err(mpsc::RecvTimeoutError::Timeout) = done_rx.recv_timeout(Duration::from_millis(2)) {
+                    if self.stop.load(Ordering::Relaxed) || hard_deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        abort.store(true, Ordering::Relaxed);
+                        break;
+                    }
+                }
+            });
+            loop {
+                // One pass per line, every pass leaves out the moves found by the passes before
+                let mut ranking: Vec<(f32, Vec<ChessMove>)> = Vec::new();
+                while ranking.len() < lines.min(order.len()) && !abort.load(Ordering::Relaxed) {
+                    let candidates: Vec<ChessMove> = order.iter().filter(|m| !ranking.iter().any(|(_, pv)| pv[0] == **m)).copied().collect();
+                    let (found, pass_stats) = self.search_root(&candidates, fifty_move_counter, depth, max_depth, abort);
+                    stats += pass_stats;
+                    if let Some(found) = found {
+                        ranking.push(found);
+                    }
+                }
+                if abort.load(Ordering::Relaxed) {
+                    break;
+                }
+                let (best, best_move) = (ranking[0].0, ranking[0].1[0]);
+                let iteration_nodes = stats.nodes - previous_total;
+                branching_factor = previous_iteration.map(|nodes| iteration_nodes as f64 / nodes as f64);
+                previous_total = stats.nodes;
+                previous_iteration = Some(iteration_nodes.max(1));
+                if let Some(updates) = &self.updates {
+                    let info = self.search_info(&ranking, depth, max_depth, stats, branching_factor, time_manager.elapsed());
+                    let _ = updates.send(SearchUpdate::Info(info));
+                }
+                // The next iteration starts with the best moves of this one
+                order.sort_by_key(|m| ranking.iter().position(|(_, pv)| pv[0] == *m).unwrap_or(ranking.len()));
+                if shallow.is_none() {
+                    shallow = Some(ranking.clone());
+                }
+                result = Some(ranking);
+                completed = (depth, max_depth);
+                time_manager.update(best_move, best);
+                if time_manager.should_stop() || depth >= 50 || self.skill.node_limit().is_some_and(|limit| stats.nodes >= limit) {
+                    break;
+                }
+                if max_depth <= depth + 6 {
+                    max_depth += 2;
+                } else {
+                    depth += 2;
+                }
+                if self.depth_limit().is_some_and(|(depth_limit, max_depth_limit)| depth > depth_limit || max_depth > max_depth_limit) {
+                    break;
+                }
             }
-            return self.best_move(fifty_move_counter, depth + 2, max_depth, time,start);
+            drop(done_tx);
+        });
+        // Without a single finished iteration one ply is searched to the end, that takes no time
+        let ranking = match result {
+            Some(ranking) => ranking,
+            None => {
+                let (found, pass_stats) = self.search_root(&order, fifty_move_counter, 1, 1, &AtomicBool::new(false));
+                stats += pass_stats;
+                completed = (1, 1);
+                vec![found.unwrap_or((0.0, vec![order[0]]))]
+            },
+        };
+        let (depth, max_depth) = completed;
+        let info = self.search_info(&ranking, depth, max_depth, stats, branching_factor, time_manager.elapsed());
+
+        // Now and then a weakened engine plays as if it had only seen the first iteration
+        let choices = match shallow {
+            Some(shallow) if rng.gen_bool(self.skill.blunder_chance()) => shallow,
+            _ => ranking,
+        };
+        let scores: Vec<f32> = choices.iter().map(|(eval, _)| *eval).collect();
+        let best_move = choices[self.skill.choose(&scores, &mut rng)].1[0];
+
+        if self.stop.load(Ordering::Relaxed) {
+            return Some((best_move, fifty_move_counter, info));
         }
         
-        if self.brett.piece_on(best_move.get_source()) == Some(chess::Piece::Pawn) || self.brett.piece_on(best_move.get_dest()) != None {
+        if chess960::is_zeroing(&self.brett, best_move) {
             fifty_move_counter = 0;
         } else {
             fifty_move_counter += 1;
         }
 
-        Engine::print_move(best_move, &self.brett);
-        println!("tiefe: {}, max tiefe: {}, eval: {:.2}, time: {:?}", depth, max_depth, factor * best, SystemTime::now().duration_since(start).unwrap());
+        Some((best_move, fifty_move_counter, info))
+    }
 
-        (best_move, fifty_move_counter)
+    fn search_info(&self, ranking: &[(f32, Vec<ChessMove>)], depth: u64, max_depth: u64, stats: SearchStats, branching_factor: Option<f64>, time: Duration) -> SearchInfo {
+        let factor = match self.brett.side_to_move() {
+            chess::Color::Black => -1.0,
+            chess::Color::White =>  1.0,
+        };
+        let lines = ranking.iter()
+            .take(self.multi_pv)
+            .map(|(eval, pv)| Line { eval: factor * eval, pv: pv.clone() })
+            .collect();
+        let (best, pv) = ranking[0].clone();
+        SearchInfo { eval: factor * best, depth, max_depth, stats, branching_factor, time, tablebase: false, root: self.brett, castling: self.castling, pv, lines }
+    }
+
+    // Finds the best of `candidates`, the score is from the point of view of the side to move.
+    // The first candidate is searched alone, its score is the lower bound for the others
+    // which are searched in parallel and only get an exact score if they are better.
+    fn search_root(&self, candidates: &[ChessMove], fifty_move_counter: u8, depth: u64, max_depth: u64, abort: &AtomicBool) -> (Option<(f32, Vec<ChessMove>)>, SearchStats) {
+        let (pv, eval, mut stats) = self.search_root_move(candidates[0], f32::NEG_INFINITY, fifty_move_counter, depth, max_depth, abort);
+        let mut best = (eval, pv);
+
+        let mut moves: Vec<(f32, Vec<ChessMove>, SearchStats)> = Vec::new();
+        candidates[1..].par_iter()
+        .map(|m| {
+            let (pv, eval, move_stats) = self.search_root_move(*m, best.0, fifty_move_counter, depth, max_depth, abort);
+            (eval, pv, move_stats)
+        }).collect_into_vec(&mut moves);
+
+        for (eval, pv, move_stats) in moves {
+            stats += move_stats;
+            if eval > best.0 {
+                best = (eval, pv);
+            }
+        }
+        // The search may have been cut short
+        if abort.load(Ordering::Relaxed) {
+            return (None, stats);
+        }
+        (Some(best), stats)
+    }
+
+    fn search_root_move(&self, m: ChessMove, bound: f32, fifty_move_counter: u8, depth: u64, max_depth: u64, abort: &AtomicBool) -> (Vec<ChessMove>, f32, SearchStats) {
+        let fifty = if chess960::is_zeroing(&self.brett, m) {
+            0
+        } else {
+            fifty_move_counter + 1
+        };
+        let (brett, _) = chess960::play(&self.brett, self.castling, m);
+        let mut ctx = SearchContext { eval_map: HashMap::new(), abort, depth, stats: SearchStats::default(), pv: Vec::new() };
+        // minmax scores from White's point of view
+        let (factor, alpha, beta, maximizing_player) = match self.brett.side_to_move() {
+            chess::Color::White => ( 1.0, bound, f32::INFINITY, false),
+            chess::Color::Black => (-1.0, f32::NEG_INFINITY, -bound, true),
+        };
+        let eval = factor * Engine::minmax(&brett, fifty, depth - 1, max_depth, alpha, beta, maximizing_player, &mut ctx, 2);
+        let mut pv = vec![m];
+        pv.extend_from_slice(&ctx.pv[2]);
+        (pv, eval, ctx.stats)
     }
 
     fn eval_board(brett: &chess::Board, fifty_move_counter: u8) -> f32 {
@@ -212,7 +500,7 @@ impl Engine {
             },
             chess::BoardStatus::Ongoing => {},
         }
-        if fifty_move_counter >= 50 {
+        if fifty_move_counter >= 100 {
             return 0.0;
         }
         let mut eval = 0.0;
@@ -237,240 +525,47 @@ impl Engine {
         eval
     }
 
-    fn print_move(input: ChessMove, brett: &chess::Board) {
-        if input.get_source() == chess::Square::E1 && input.get_dest() == chess::Square::G1 {
-            println!("{:?} spielt: {}",brett.side_to_move() , "O-O");
-            return;
-        }
-        if input.get_source() == chess::Square::E1 && input.get_dest() == chess::Square::C1 {
-            println!("{:?} spielt: {}",brett.side_to_move() , "O-O-O");
-            return;
-        }
-        if input.get_source() == chess::Square::E8 && input.get_dest() == chess::Square::G8 {
-            println!("{:?} spielt: {}",brett.side_to_move() , "O-O");
-            return;
-        }
-        if input.get_source() == chess::Square::E8 && input.get_dest() == chess::Square::C8 {
-            println!("{:?} spielt: {}",brett.side_to_move() , "O-O-O");
-            return;
-        }
-        let piece = match brett.piece_on(input.get_source()).unwrap() {
-            chess::Piece::Pawn => san_rs::Piece::Pawn,
-            chess::Piece::Knight => san_rs::Piece::Knight,
-            chess::Piece::Bishop => san_rs::Piece::Bishop,
-            chess::Piece::Rook => san_rs::Piece::Rook,
-            chess::Piece::Queen => san_rs::Piece::Queen,
-            chess::Piece::King => san_rs::Piece::King,
-        };
-    
-        let source = san_rs::Position::new(Some(input.get_source().get_file().to_index() as usize), Some(7 - input.get_source().get_rank().to_index() as usize));
-    
-        let target: san_rs::Position = san_rs::Position::new(Some(input.get_dest().get_file().to_index() as usize), Some(7 - input.get_dest().get_rank().to_index() as usize)); 
-        let move_kind = san_rs::MoveKind::Normal(source, target); 
-        let mut m = san_rs::Move::new(piece, move_kind);
-        if (input.get_dest().get_rank().to_index() == 0 || input.get_dest().get_rank().to_index() == 7) && brett.piece_on(input.get_source()).unwrap() == chess::Piece::Pawn {
-            let promotion = match input.get_promotion() {
-                Some(chess::Piece::Queen) => Some(san_rs::Piece::Queen),
-                Some(chess::Piece::Rook) => Some(san_rs::Piece::Rook),
-                Some(chess::Piece::Bishop) => Some(san_rs::Piece::Bishop),
-                Some(chess::Piece::Knight) => Some(san_rs::Piece::Knight),
-                _ => None,
-            };
-            m.promotion = promotion; 
-        }
-        m.is_capture = brett.piece_on(input.get_dest()) != None;
-    
-        if brett.checkers().0 != 0 {
-            m.check_type = Some(san_rs::CheckType::Check);
-        }
-            
-        m.check_type = match brett.status() {
-            BoardStatus::Checkmate => Some(san_rs::CheckType::Mate),
-            _ => None,
-        };
-        let san_string = m.compile();
-        println!("{:?} spielt: {}",brett.side_to_move() , san_string);
-    }
-    
-    fn best_move_table(brett: &chess::Board, fifty_move_counter: u32, tables: &shakmaty_syzygy::Tablebase<shakmaty::Chess>) -> Option<(chess::ChessMove, f32)> {
-        if brett.castle_rights(chess::Color::White) != chess::CastleRights::NoRights || brett.castle_rights(chess::Color::Black) != chess::CastleRights::NoRights {
-            return None;
-        }
-        if brett.color_combined(chess::Color::White).0.count_ones() + brett.color_combined(chess::Color::Black).0.count_ones() > 5 || brett.color_combined(chess::Color::White).0.count_ones() + brett.color_combined(chess::Color::Black).0.count_ones() < 3  {
-            return None;
-        }
-
-        let bitboard_white = shakmaty::Bitboard::from(brett.color_combined(chess::Color::White).0);
-        let bitboard_black = shakmaty::Bitboard::from(brett.color_combined(chess::Color::Black).0);
-    
-        let pawn_bitbopard = shakmaty::Bitboard::from(brett.pieces(chess::Piece::Pawn).0);
-        let knight_bitbopard = shakmaty::Bitboard::from(brett.pieces(chess::Piece::Knight).0);
-        let bishop_bitbopard = shakmaty::Bitboard::from(brett.pieces(chess::Piece::Bishop).0);
-        let rook_bitbopard = shakmaty::Bitboard::from(brett.pieces(chess::Piece::Rook).0);
-        let queen_bitbopard = shakmaty::Bitboard::from(brett.pieces(chess::Piece::Queen).0);
-        let king_bitbopard = shakmaty::Bitboard::from(brett.pieces(chess::Piece::King).0);
-        
-        let board = shakmaty::Board::from_bitboards(shakmaty::ByRole { pawn: pawn_bitbopard, knight: knight_bitbopard, bishop: bishop_bitbopard, rook: rook_bitbopard, queen: queen_bitbopard, king: king_bitbopard } , shakmaty::ByColor { black: bitboard_black, white: bitboard_white });
-        let turn = match brett.side_to_move() {
-            chess::Color::White => shakmaty::Color::White,
-            chess::Color::Black => shakmaty::Color::Black,  
-        };
-        let casteling_rights = 0u64;
-    
-        let ep_square = match brett.en_passant() {
-            Some(sq) => Some(shakmaty::Square::from_coords(shakmaty::File::new(sq.get_file().to_index() as u32), shakmaty::Rank::new(sq.get_rank().to_index() as u32))),
-            None => None,
-        };
-    
-        let fifty_move_rule = fifty_move_counter;
-        let full_moves = NonZeroU32::new(1).unwrap();
-    
-        let setup = shakmaty::Setup{ 
-            board: board,
-            promoted: shakmaty::Bitboard::from(0),
-            pockets: None,
-            turn: turn,
-            castling_rights: shakmaty::Bitboard::from(casteling_rights),
-            ep_square: ep_square,
-            remaining_checks: None,
-            halfmoves: fifty_move_rule,
-            fullmoves: full_moves,
-        };
-    
-        let pos_raw = shakmaty::Chess::from_setup(setup, shakmaty::CastlingMode::Standard);
-        let pos = pos_raw.unwrap();
-
-        let reward = match brett.side_to_move() {
-            chess::Color::White =>  100000.0,
-            chess::Color::Black => -100000.0,
-        };
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
 
-        let eval = if let Ok(wdl) = tables.probe_wdl_after_zeroing(&pos) {
-            match wdl {
-                shakmaty_syzygy::Wdl::Loss => -reward,
-                shakmaty_syzygy::Wdl::BlessedLoss => 0.0,
-                shakmaty_syzygy::Wdl::Draw => 0.0,
-                shakmaty_syzygy::Wdl::CursedWin => 0.0,
-                shakmaty_syzygy::Wdl::Win => reward,
-            }
-        } else {
-            0.0
-        };
-        
-
-        match tables.best_move(&pos) {
-            Ok(Some((m,_))) => {
-                match m {
-                    shakmaty::Move::Normal { from, to, promotion, .. } => {
-                        let source_file = match from.file()  {
-                            shakmaty::File::A => chess::File::A,
-                            shakmaty::File::B => chess::File::B,
-                            shakmaty::File::C => chess::File::C,
-                            shakmaty::File::D => chess::File::D,
-                            shakmaty::File::E => chess::File::E,
-                            shakmaty::File::F => chess::File::F,
-                            shakmaty::File::G => chess::File::G,
-                            shakmaty::File::H => chess::File::H,
-                        };
-                        let source_rank = match from.rank()  {
-                            shakmaty::Rank::First => chess::Rank::First,
-                            shakmaty::Rank::Second => chess::Rank::Second,
-                            shakmaty::Rank::Third => chess::Rank::Third,
-                            shakmaty::Rank::Fourth => chess::Rank::Fourth,
-                            shakmaty::Rank::Fifth => chess::Rank::Fifth,
-                            shakmaty::Rank::Sixth => chess::Rank::Sixth,
-                            shakmaty::Rank::Seventh => chess::Rank::Seventh,
-                            shakmaty::Rank::Eighth => chess::Rank::Eighth,
-                        };
-                        
-                        let dest_file = match to.file()  {
-                            shakmaty::File::A => chess::File::A,
-                            shakmaty::File::B => chess::File::B,
-                            shakmaty::File::C => chess::File::C,
-                            shakmaty::File::D => chess::File::D,
-                            shakmaty::File::E => chess::File::E,
-                            shakmaty::File::F => chess::File::F,
-                            shakmaty::File::G => chess::File::G,
-                            shakmaty::File::H => chess::File::H,
-                        };
-    
-                        let dest_rank = match to.rank()  {
-                            shakmaty::Rank::First => chess::Rank::First,
-                            shakmaty::Rank::Second => chess::Rank::Second,
-                            shakmaty::Rank::Third => chess::Rank::Third,
-                            shakmaty::Rank::Fourth => chess::Rank::Fourth,
-                            shakmaty::Rank::Fifth => chess::Rank::Fifth,
-                            shakmaty::Rank::Sixth => chess::Rank::Sixth,
-                            shakmaty::Rank::Seventh => chess::Rank::Seventh,
-                            shakmaty::Rank::Eighth => chess::Rank::Eighth,
-                        }; 
-    
-                        let prom = match promotion {
-                            Some(shakmaty::Role::Queen) => Some(chess::Piece::Queen),
-                            Some(shakmaty::Role::Rook) => Some(chess::Piece::Rook),
-                            Some(shakmaty::Role::Bishop) => Some(chess::Piece::Bishop),
-                            Some(shakmaty::Role::Knight) => Some(chess::Piece::Knight),
-                            Some(shakmaty::Role::King) => Some(chess::Piece::King),
-                            Some(shakmaty::Role::Pawn) => Some(chess::Piece::Pawn),
-                            None => None,
-                        };
-                        Some((ChessMove::new(chess::Square::make_square(source_rank, source_file), chess::Square::make_square(dest_rank, dest_file), prom),eval))
-                    },
-                    shakmaty::Move::EnPassant { from, to } => {
-                        let source_file = match from.file()  {
-                            shakmaty::File::A => chess::File::A,
-                            shakmaty::File::B => chess::File::B,
-                            shakmaty::File::C => chess::File::C,
-                            shakmaty::File::D => chess::File::D,
-                            shakmaty::File::E => chess::File::E,
-                            shakmaty::File::F => chess::File::F,
-                            shakmaty::File::G => chess::File::G,
-                            shakmaty::File::H => chess::File::H,
-                        };
-                        let source_rank = match from.rank()  {
-                            shakmaty::Rank::First => chess::Rank::First,
-                            shakmaty::Rank::Second => chess::Rank::Second,
-                            shakmaty::Rank::Third => chess::Rank::Third,
-                            shakmaty::Rank::Fourth => chess::Rank::Fourth,
-                            shakmaty::Rank::Fifth => chess::Rank::Fifth,
-                            shakmaty::Rank::Sixth => chess::Rank::Sixth,
-                            shakmaty::Rank::Seventh => chess::Rank::Seventh,
-                            shakmaty::Rank::Eighth => chess::Rank::Eighth,
-                        };
-                        
-                        let dest_file = match to.file()  {
-                            shakmaty::File::A => chess::File::A,
-                            shakmaty::File::B => chess::File::B,
-                            shakmaty::File::C => chess::File::C,
-                            shakmaty::File::D => chess::File::D,
-                            shakmaty::File::E => chess::File::E,
-                            shakmaty::File::F => chess::File::F,
-                            shakmaty::File::G => chess::File::G,
-                            shakmaty::File::H => chess::File::H,
-                        };
-    
-                        let dest_rank = match to.rank()  {
-                            shakmaty::Rank::First => chess::Rank::First,
-                            shakmaty::Rank::Second => chess::Rank::Second,
-                            shakmaty::Rank::Third => chess::Rank::Third,
-                            shakmaty::Rank::Fourth => chess::Rank::Fourth,
-                            shakmaty::Rank::Fifth => chess::Rank::Fifth,
-                            shakmaty::Rank::Sixth => chess::Rank::Sixth,
-                            shakmaty::Rank::Seventh => chess::Rank::Seventh,
-                            shakmaty::Rank::Eighth => chess::Rank::Eighth,
-                        }; 
-                        let prom = None;
-                        Some((ChessMove::new(chess::Square::make_square(source_rank, source_file), chess::Square::make_square(dest_rank, dest_file), prom), eval))
-                    },
-                    _ => None,
-                }
-            },
-            _ => {
-                println!("Tablebase not found");
-                None},
+    #[test]
+    fn no_move_when_the_game_is_over() {
+        let mated = chess::Board::from_str("R5k1/5ppp/8/8/8/8/5PPP/6K1 b - - 1 1").unwrap();
+        let stalemate = chess::Board::from_str("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        for brett in [mated, stalemate] {
+            let mut engine = Engine::new(brett);
+            engine.set_tablebase("");
+            assert!(engine.best_move(0, 4, 10, TimeManager::fixed(Duration::from_millis(100))).is_none());
         }
-    
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn searched_move_without_time() {
+        // The black queen hangs
+        let brett = chess::Board::from_str("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let mut engine = Engine::new(brett);
+        engine.set_tablebase("");
+        let (chess_move, _, info) = engine.best_move(0, 4, 10, TimeManager::fixed(Duration::ZERO)).unwrap();
+        assert_eq!(chess_move, ChessMove::new(chess::Square::D1, chess::Square::D5, None));
+        assert!(info.depth >= 1);
+    }
+
+    #[test]
+    fn fifty_move_draw_at_ply_100() {
+        // From a halfmove clock of 99 every move of the rook reaches ply 100
+        let brett = chess::Board::from_str("4k3/8/8/8/8/8/R7/4K3 w - - 0 1").unwrap();
+        let mut engine = Engine::new(brett);
+        engine.set_tablebase("");
+        engine.set_depth_limit(1);
+        let (_, fifty, info) = engine.best_move(98, 1, 1, TimeManager::infinite()).unwrap();
+        assert_eq!(fifty, 99);
+        assert!(info.eval > 0.0);
+        let (_, fifty, info) = engine.best_move(99, 1, 1, TimeManager::infinite()).unwrap();
+        assert_eq!(fifty, 100);
+        assert_eq!(info.eval, 0.0);
+    }
+}
@@ -0,0 +1,215 @@
+use chess::Board;
+use chess::ChessMove;
+use std::str::FromStr;
+use std::time::Duration;
+use std::time::Instant;
+use crate::config::Config;
+use crate::engine::Engine;
+use crate::notation;
+use crate::time_manager::TimeManager;
+
+/// One line of an EPD suite with the operations the runner understands.
+pub struct EpdPosition {
+    pub id: String,
+    pub brett: Board,
+    pub best_moves: Vec<ChessMove>,
+    pub avoid_moves: Vec<ChessMove>,
+}
+
+pub struct EpdResult {
+    pub id: String,
+    pub played: String,
+    pub expected: String,
+    pub solved: bool,
+    pub time: Duration,
+}
+
+impl EpdPosition {
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = line.splitn(5, ' ').collect();
+        if fields.len() < 4 {
+            return Err(format!("Not an EPD line: {}", line));
+        }
+        let fen = format!("{} 0 1", fields[..4].join(" "));
+        let brett = Board::from_str(&fen).map_err(|e| format!("Invalid position {}: {}", fen, e))?;
+        let mut position = EpdPosition { id: String::new(), brett, best_moves: Vec::new(), avoid_moves: Vec::new() };
+
+        for operation in fields.get(4).unwrap_or(&"").split(';') {
+            let mut tokens = operation.split_whitespace();
+            match tokens.next() {
+                Some("id") => position.id = tokens.collect::<Vec<&str>>().join(" ").trim_matches('"').to_string(),
+                Some(opcode @ ("bm" | "am")) => {
+                    let moves = tokens
+                        .map(|san| notation::parse_san(&brett, san))
+                        .collect::<Result<Vec<ChessMove>, String>>()?;
+                    match opcode {
+                        "bm" => position.best_moves = moves,
+                        _ => position.avoid_moves = moves,
+                    }
+                },
+                _ => (),
+            }
+        }
+        if position.best_moves.is_empty() && position.avoid_moves.is_empty() {
+            return Err(format!("Neither bm nor am in {}", line));
+        }
+        Ok(position)
+    }
+
+    pub fn is_solved_by(&self, chess_move: ChessMove) -> bool {
+        (self.best_moves.is_empty() || self.best_moves.contains(&chess_move)) && !self.avoid_moves.contains(&chess_move)
+    }
+
+    fn expected(&self) -> String {
+        let sans = |moves: &[ChessMove]| moves.iter().map(|m| notation::san(*m, &self.brett)).collect::<Vec<String>>().join(" ");
+        match (self.best_moves.is_empty(), self.avoid_moves.is_empty()) {
+            (false, true) => format!("bm {}", sans(&self.best_moves)),
+            (true, false) => format!("am {}", sans(&self.avoid_moves)),
+            _ => format!("bm {}; am {}", sans(&self.best_moves), sans(&self.avoid_moves)),
+        }
+    }
+}
+
+pub fn solve(position: &EpdPosition, time: Option<Duration>, depth: Option<u64>, config: &Config) -> EpdResult {
+    let mut engine = Engine::new(position.brett);
+    engine.set_tablebase(&config.tablebase.path);
+    if let Some(depth) = depth {
+        engine.set_depth_limit(depth);
+    }
+    let time_manager = match time {
+        Some(time) => TimeManager::fixed(time),
+        None => TimeManager::infinite(),
+    };
+    let start = Instant::now();
+    let chess_move = engine.best_move(0, config.engine.depth, config.engine.max_depth, time_manager).map(|(chess_move, _, _)| chess_move);
+    EpdResult {
+        id: position.id.clone(),
+        played: chess_move.map_or("-".to_string(), |m| notation::san(m, &position.brett)),
+        expected: position.expected(),
+        solved: chess_move.is_some_and(|m| position.is_solved_by(m)),
+        time: start.elapsed(),
+    }
+}
+
+/// `epd <file> [--time <ms>] [--depth <n>] [--json]`, without limits every
+/// position gets one second.
+pub fn run(args: &[String], config: &Config) -> Result<(), String> {
+    let usage = "Usage: epd <file> [--time <ms>] [--depth <n>] [--json]";
+    let path = args.first().ok_or(usage)?;
+    let value = |name: &str| -> Result<Option<u64>, String> {
+        match args.iter().position(|a| a == name) {
+            Some(i) => args.get(i + 1).and_then(|v| v.parse().ok()).map(Some).ok_or(usage.to_string()),
+            None => Ok(None),
+        }
+    };
+    // Depth 0 would search nothing at all
+    let depth = match value("--depth")? {
+        Some(0) => return Err(format!("{}, the depth is at least 1", usage)),
+        depth => depth,
+    };
+    let time = match (value("--time")?, depth) {
+        (Some(ms), _) => Some(Duration::from_millis(ms)),
+        (None, Some(_)) => None,
+        (None, None) => Some(Duration::from_secs(1)),
+    };
+    let json = args.iter().any(|a| a == "--json");
+
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let positions = content.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(EpdPosition::parse)
+        .collect::<Result<Vec<EpdPosition>, String>>()?;
+
+    let start = Instant::now();
+    let mut results = Vec::new();
+    for (i, position) in positions.iter().enumerate() {
+        let result = solve(position, time, depth, config);
+        if !json {
+            println!("{:>4}/{}  {:<12} {:<6} {:<8} {} ({:.2} s)", i + 1, positions.len(), result.id, if result.solved { "ok" } else { "FAIL" }, result.played, result.expected, result.time.as_secs_f64());
+        }
+        results.push(result);
+    }
+    let total = start.elapsed();
+    let solved = results.iter().filter(|r| r.solved).count();
+
+    if json {
+        println!("{}", to_json(&results, total));
+        return Ok(());
+    }
+    let failed: Vec<&str> = results.iter().filter(|r| !r.solved).map(|r| r.id.as_str()).collect();
+    println!();
+    println!("Solved: {}/{}", solved, results.len());
+    if !failed.is_empty() {
+        println!("Failed: {}", failed.join(", "));
+    }
+    println!("Time: {:.2} s", total.as_secs_f64());
+    Ok(())
+}
+
+fn to_json(results: &[EpdResult], total: Duration) -> String {
+    let positions: Vec<String> = results.iter()
+        .map(|r| format!(
+            "{{\"id\":{},\"solved\":{},\"played\":{},\"expected\":{},\"time\":{:.3}}}",
+            json_string(&r.id), r.solved, json_string(&r.played), json_string(&r.expected), r.time.as_secs_f64(),
+        ))
+        .collect();
+    format!(
+        "{{\"solved\":{},\"total\":{},\"time\":{:.3},\"positions\":[{}]}}",
+        results.iter().filter(|r| r.solved).count(), results.len(), total.as_secs_f64(), positions.join(","),
+    )
+}
+
+fn json_string(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_solve() {
+        let position = EpdPosition::parse("4k3/8/8/3q4/8/8/8/3RK3 w - - bm Rxd5; am Kf2; id \"hanging queen\";").unwrap();
+        assert_eq!(position.id, "hanging queen");
+        assert_eq!(position.best_moves, vec![ChessMove::new(chess::Square::D1, chess::Square::D5, None)]);
+        assert_eq!(position.avoid_moves, vec![ChessMove::new(chess::Square::E1, chess::Square::F2, None)]);
+        assert_eq!(position.expected(), "bm Rxd5; am Kf2");
+        assert!(position.is_solved_by(ChessMove::new(chess::Square::D1, chess::Square::D5, None)));
+        assert!(!position.is_solved_by(ChessMove::new(chess::Square::E1, chess::Square::F1, None)));
+
+        // Only moves to avoid, every other move solves it
+        let position = EpdPosition::parse("4k3/8/8/3q4/8/8/8/3RK3 w - - am Kf2;").unwrap();
+        assert!(position.id.is_empty());
+        assert!(position.is_solved_by(ChessMove::new(chess::Square::E1, chess::Square::F1, None)));
+        assert!(!position.is_solved_by(ChessMove::new(chess::Square::E1, chess::Square::F2, None)));
+
+        assert!(EpdPosition::parse("4k3/8/8/3q4/8/8/8/3RK3 w - - id \"nothing\";").is_err());
+        assert!(EpdPosition::parse("4k3/8/8/3q4/8/8/8/3RK3 w - - bm Qxd5;").is_err());
+    }
+
+    #[test]
+    fn json_escaping() {
+        assert_eq!(json_string("say \"hi\" \\ bye\n"), "\"say \\\"hi\\\" \\\\ bye\\u000a\"");
+        let result = EpdResult { id: "a\"b".to_string(), played: "Rxd5".to_string(), expected: "bm Rxd5".to_string(), solved: true, time: Duration::from_millis(1500) };
+        assert_eq!(
+            to_json(&[result], Duration::from_secs(2)),
+            "{\"solved\":1,\"total\":1,\"time\":2.000,\"positions\":[{\"id\":\"a\\\"b\",\"solved\":true,\"played\":\"Rxd5\",\"expected\":\"bm Rxd5\",\"time\":1.500}]}",
+        );
+    }
+
+    #[test]
+    fn depth_zero_is_rejected() {
+        let args: Vec<String> = ["file", "--depth", "0"].iter().map(|a| a.to_string()).collect();
+        assert!(run(&args, &Config::default()).is_err());
+    }
+}
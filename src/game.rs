@@ -0,0 +1,387 @@
+use chess::Board;
+use chess::BoardStatus;
+use chess::ChessMove;
+use chess::Color;
+use chess::Square;
+use std::str::FromStr;
+use crate::chess960;
+use crate::chess960::Castling;
+use crate::clock::Clock;
+use crate::clock::TimeControl;
+use crate::game_record::GameRecord;
+use crate::skill::Skill;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Player {
+    Human,
+    Engine(Skill),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Outcome {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    FiftyMoveRule,
+    Repetition,
+    InsufficientMaterial,
+    TimeForfeit,
+    // Variant wins, see `variant`
+    KingOfTheHill,
+    ThreeChecks,
+    KingExploded,
+}
+
+impl Outcome {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Outcome::WhiteWins => "White wins",
+            Outcome::BlackWins => "Black wins",
+            Outcome::Draw => "draw",
+        }
+    }
+
+    pub fn win_for(color: Color) -> Self {
+        match color {
+            Color::White => Outcome::WhiteWins,
+            Color::Black => Outcome::BlackWins,
+        }
+    }
+}
+
+impl Termination {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Termination::Checkmate => "Checkmate",
+            Termination::Stalemate => "Stalemate",
+            Termination::FiftyMoveRule => "Fifty-move rule",
+            Termination::Repetition => "Threefold repetition",
+            Termination::InsufficientMaterial => "Insufficient material",
+            Termination::TimeForfeit => "Time forfeit",
+            Termination::KingOfTheHill => "King in the center",
+            Termination::ThreeChecks => "Three checks",
+            Termination::KingExploded => "King exploded",
+        }
+    }
+}
+
+/// The rules flow of one game: moves, clocks, who plays which side and how
+/// the game ended. Searching for engine moves is left to the caller.
+pub struct Game {
+    record: GameRecord,
+    clock: Clock,
+    players: [Player; 2],
+    result: Option<(Outcome, Termination)>,
+    // In analysis the moves don't cost any time
+    clock_paused: bool,
+}
+
+impl Game {
+    pub fn new(start: Board, time_control: TimeControl, players: [Player; 2]) -> Self {
+        Game::with_halfmove_clock(start, Castling::none(), 0, time_control, players)
+    }
+
+    /// A Chess960 game from start position `index`, see `chess960::start_position`.
+    pub fn chess960(index: u16, time_control: TimeControl, players: [Player; 2]) -> Result<Self, String> {
+        let (start, castling) = chess960::start_position(index).ok_or(format!("No Chess960 start position {}", index))?;
+        Ok(Game::with_halfmove_clock(start, castling, 0, time_control, players))
+    }
+
+    /// Like `new`, but takes the halfmove clock from the FEN as well.
+    pub fn from_fen(fen: &str, time_control: TimeControl, players: [Player; 2]) -> Result<Self, String> {
+        let start = Board::from_str(fen).map_err(|e| format!("Invalid FEN {}: {}", fen, e))?;
+        let halfmove_clock = fen.split_whitespace().nth(4).and_then(|t| t.parse().ok()).unwrap_or(0);
+        Ok(Game::with_halfmove_clock(start, Castling::none(), halfmove_clock, time_control, players))
+    }
+
+    /// A Chess960 game from X-FEN or Shredder-FEN.
+    pub fn from_fen_chess960(fen: &str, time_control: TimeControl, players: [Player; 2]) -> Result<Self, String> {
+        let (start, castling, halfmove_clock, _) = chess960::from_fen(fen)?;
+        Ok(Game::with_halfmove_clock(start, castling, halfmove_clock, time_control, players))
+    }
+
+    fn with_halfmove_clock(start: Board, castling: Castling, halfmove_clock: u32, time_control: TimeControl, players: [Player; 2]) -> Self {
+        let mut game = Game {
+            record: GameRecord::new(start, castling, halfmove_clock),
+            clock: Clock::new(time_control),
+            players,
+            result: None,
+            clock_paused: false,
+        };
+        game.result = game.detect_result();
+        game
+    }
+
+    pub fn board(&self) -> Board {
+        self.record.board()
+    }
+
+    // Only Chess960 games have castling rights outside the board
+    pub fn castling(&self) -> Castling {
+        self.record.castling()
+    }
+
+    pub fn record(&self) -> &GameRecord {
+        &self.record
+    }
+
+    pub fn clock(&self) -> &Clock {
+        &self.clock
+    }
+
+    pub fn player(&self, color: Color) -> Player {
+        self.players[color.to_index()]
+    }
+
+    pub fn set_player(&mut self, color: Color, player: Player) {
+        self.players[color.to_index()] = player;
+    }
+
+    pub fn player_to_move(&self) -> Player {
+        self.player(self.board().side_to_move())
+    }
+
+    pub fn result(&self) -> Option<(Outcome, Termination)> {
+        self.result
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.result.is_some()
+    }
+
+    /// The halfmove clock for the engine, in plies
+    pub fn fifty_move_counter(&self) -> u8 {
+        self.record.halfmove_clock().min(100) as u8
+    }
+
+    /// Moves of the piece on `square`, none once the game is over.
+    pub fn legal_moves_from(&self, square: Square) -> Vec<ChessMove> {
+        if self.is_over() {
+            return Vec::new();
+        }
+        chess960::legal_moves(&self.board(), self.castling()).into_iter()
+            .filter(|m| m.get_source() == square)
+            .collect()
+    }
+
+    /// Plays `chess_move` at the current ply and hands the clock to the opponent.
+    pub fn play(&mut self, chess_move: ChessMove) -> Result<(), String> {
+        let brett = self.board();
+        if self.is_over() {
+            return Err("The game is over".to_string());
+        }
+        if !chess960::legal(&brett, self.castling(), chess_move) {
+            return Err(format!("Illegal move {} in {}", chess_move, brett));
+        }
+        self.record.play(chess_move);
+        if !self.clock_paused {
+            self.clock.press(brett.side_to_move());
+        }
+        self.result = self.detect_result();
+        if self.is_over() {
+            self.clock.stop();
+        }
+        Ok(())
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let undone = self.record.undo();
+        self.after_navigation();
+        undone
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let redone = self.record.redo();
+        self.after_navigation();
+        redone
+    }
+
+    pub fn goto(&mut self, ply: usize) {
+        self.record.goto(ply);
+        self.after_navigation();
+    }
+
+    /// Undoes moves until a human is to move again, at least one.
+    pub fn take_back(&mut self) -> bool {
+        if !self.record.undo() {
+            return false;
+        }
+        while self.player_to_move() != Player::Human && self.record.undo() {}
+        self.after_navigation();
+        true
+    }
+
+    /// Ends the game if a flag fell, needs to be called while the clock runs.
+    pub fn check_clock(&mut self) {
+        if self.is_over() {
+            return;
+        }
+        if let Some(flagged) = self.clock.flagged() {
+            self.clock.stop();
+            // Flagging only loses if the opponent still has something to mate with
+            let outcome = match self.board().color_combined(!flagged).popcnt() == 1 {
+                true => Outcome::Draw,
+                false => Outcome::win_for(!flagged),
+            };
+            self.result = Some((outcome, Termination::TimeForfeit));
+        }
+    }
+
+    pub fn pause_clock(&mut self) {
+        self.clock_paused = true;
+        self.clock.stop();
+    }
+
+    pub fn resume_clock(&mut self) {
+        self.clock_paused = false;
+        if !self.record.is_empty() && self.record.at_end() && !self.is_over() {
+            self.clock.start(self.board().side_to_move());
+        }
+    }
+
+    // Looking at earlier moves uses no time, the clocks only run at the last position
+    fn after_navigation(&mut self) {
+        self.result = self.detect_result();
+        if self.is_over() || !self.record.at_end() {
+            self.clock.stop();
+        } else if !self.clock_paused && self.clock.flagged().is_none() {
+            self.clock.start(self.board().side_to_move());
+        }
+    }
+
+    fn detect_result(&self) -> Option<(Outcome, Termination)> {
+        let brett = self.board();
+        match brett.status() {
+            BoardStatus::Checkmate => return Some((Outcome::win_for(!brett.side_to_move()), Termination::Checkmate)),
+            // The chess crate doesn't know that a Chess960 king may still castle
+            BoardStatus::Stalemate if chess960::castling_moves(&brett, self.castling()).is_empty() => return Some((Outcome::Draw, Termination::Stalemate)),
+            BoardStatus::Stalemate | BoardStatus::Ongoing => (),
+        }
+        if self.record.halfmove_clock() >= 100 {
+            return Some((Outcome::Draw, Termination::FiftyMoveRule));
+        }
+        let castling = self.castling();
+        if self.record.positions().filter(|(b, c)| b.get_hash() == brett.get_hash() && *c == castling).count() >= 3 {
+            return Some((Outcome::Draw, Termination::Repetition));
+        }
+        if insufficient_material(&brett) {
+            return Some((Outcome::Draw, Termination::InsufficientMaterial));
+        }
+        None
+    }
+}
+
+// Only kings, or kings and a single minor piece
+fn insufficient_material(brett: &Board) -> bool {
+    let minors = brett.pieces(chess::Piece::Knight) | brett.pieces(chess::Piece::Bishop);
+    let heavy = brett.pieces(chess::Piece::Pawn) | brett.pieces(chess::Piece::Rook) | brett.pieces(chess::Piece::Queen);
+    heavy.popcnt() == 0 && minors.popcnt() <= 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn human_game() -> Game {
+        Game::new(Board::default(), TimeControl::Unlimited, [Player::Human, Player::Human])
+    }
+
+    fn play(game: &mut Game, moves: &str) {
+        for m in moves.split_whitespace() {
+            game.play(ChessMove::from_str(m).unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn checkmate_ends_the_game() {
+        let mut game = human_game();
+        play(&mut game, "f2f3 e7e5 g2g4 d8h4");
+        assert_eq!(game.result(), Some((Outcome::BlackWins, Termination::Checkmate)));
+        assert!(game.play(ChessMove::from_str("a2a3").unwrap()).is_err());
+        assert!(game.legal_moves_from(Square::A2).is_empty());
+        assert!(game.undo());
+        assert_eq!(game.result(), None);
+        assert!(game.redo());
+        assert!(game.is_over());
+    }
+
+    #[test]
+    fn illegal_moves_are_rejected() {
+        let mut game = human_game();
+        assert!(game.play(ChessMove::from_str("e2e5").unwrap()).is_err());
+        assert_eq!(game.record().len(), 0);
+    }
+
+    #[test]
+    fn legal_moves_from_a_square() {
+        let game = human_game();
+        assert_eq!(game.legal_moves_from(Square::G1).len(), 2);
+        assert_eq!(game.legal_moves_from(Square::E2).len(), 2);
+        assert!(game.legal_moves_from(Square::E1).is_empty());
+        assert!(game.legal_moves_from(Square::E7).is_empty());
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let mut game = human_game();
+        play(&mut game, "g1f3 g8f6 f3g1 f6g8 g1f3 g8f6 f3g1");
+        assert_eq!(game.result(), None);
+        play(&mut game, "f6g8");
+        assert_eq!(game.result(), Some((Outcome::Draw, Termination::Repetition)));
+    }
+
+    #[test]
+    fn fifty_move_rule() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/R7/4K3 w - - 98 80", TimeControl::Unlimited, [Player::Human, Player::Human]).unwrap();
+        assert_eq!(game.fifty_move_counter(), 98);
+        play(&mut game, "a2a3");
+        assert_eq!(game.result(), None);
+        play(&mut game, "e8d8");
+        assert_eq!(game.result(), Some((Outcome::Draw, Termination::FiftyMoveRule)));
+        assert_eq!(game.fifty_move_counter(), 100);
+    }
+
+    #[test]
+    fn chess960_castling() {
+        let mut game = Game::from_fen_chess960("1rk2r2/pppppppp/8/8/8/8/PPPPPPPP/1RK2R2 w FBfb - 0 1", TimeControl::Unlimited, [Player::Human, Player::Human]).unwrap();
+        assert!(game.legal_moves_from(Square::C1).contains(&ChessMove::from_str("c1f1").unwrap()));
+        play(&mut game, "c1f1 c8b8");
+        assert_eq!(game.record().san_moves().collect::<Vec<_>>(), vec!["O-O", "O-O-O"]);
+        assert_eq!(game.board(), Board::from_str("2kr1r2/pppppppp/8/8/8/8/PPPPPPPP/1R3RK1 w - - 0 1").unwrap());
+        assert!(game.castling().is_empty());
+        assert!(Game::chess960(960, TimeControl::Unlimited, [Player::Human, Player::Human]).is_err());
+    }
+
+    #[test]
+    fn take_back_returns_to_the_human() {
+        let mut game = Game::new(Board::default(), TimeControl::Unlimited, [Player::Human, Player::Engine(Skill::full())]);
+        play(&mut game, "e2e4 e7e5");
+        assert!(game.take_back());
+        assert_eq!(game.record().ply(), 0);
+        assert_eq!(game.player_to_move(), Player::Human);
+    }
+
+    #[test]
+    fn clock_pauses_away_from_the_last_move() {
+        let mut game = Game::new(Board::default(), TimeControl::SuddenDeath { base: Duration::from_secs(60) }, [Player::Human, Player::Human]);
+        play(&mut game, "e2e4 e7e5");
+        assert_eq!(game.clock().running(), Some(Color::White));
+        game.goto(0);
+        assert_eq!(game.clock().running(), None);
+        assert!(game.redo());
+        assert_eq!(game.clock().running(), None);
+        assert!(game.redo());
+        assert_eq!(game.clock().running(), Some(Color::White));
+        // A move from an earlier position continues the game from there
+        game.goto(1);
+        play(&mut game, "d7d5");
+        assert_eq!(game.clock().running(), Some(Color::White));
+    }
+}
@@ -0,0 +1,180 @@
+use chess::Board;
+use chess::ChessMove;
+use crate::chess960;
+use crate::chess960::Castling;
+
+/// Every move played in a game together with the resulting position, so the
+/// GUI can take back, redo and jump to any ply without replaying moves.
+pub struct GameRecord {
+    start: Board,
+    start_castling: Castling,
+    start_halfmove_clock: u32,
+    entries: Vec<Entry>,
+    ply: usize,
+}
+
+struct Entry {
+    chess_move: ChessMove,
+    san: String,
+    brett: Board,
+    castling: Castling,
+    halfmove_clock: u32,
+}
+
+impl GameRecord {
+    /// `castling` holds the castling rights of a Chess960 game, see `chess960`.
+    pub fn new(start: Board, castling: Castling, halfmove_clock: u32) -> Self {
+        GameRecord {
+            start,
+            start_castling: castling,
+            start_halfmove_clock: halfmove_clock,
+            entries: Vec::new(),
+            ply: 0,
+        }
+    }
+
+    pub fn start(&self) -> Board {
+        self.start
+    }
+
+    pub fn board(&self) -> Board {
+        match self.ply {
+            0 => self.start,
+            ply => self.entries[ply - 1].brett,
+        }
+    }
+
+    pub fn castling(&self) -> Castling {
+        match self.ply {
+            0 => self.start_castling,
+            ply => self.entries[ply - 1].castling,
+        }
+    }
+
+    // Half moves since the last capture or pawn move
+    pub fn halfmove_clock(&self) -> u32 {
+        match self.ply {
+            0 => self.start_halfmove_clock,
+            ply => self.entries[ply - 1].halfmove_clock,
+        }
+    }
+
+    pub fn last_move(&self) -> Option<ChessMove> {
+        match self.ply {
+            0 => None,
+            ply => Some(self.entries[ply - 1].chess_move),
+        }
+    }
+
+    pub fn ply(&self) -> usize {
+        self.ply
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.ply == self.entries.len()
+    }
+
+    /// The start position and every position up to the current ply.
+    pub fn positions(&self) -> impl Iterator<Item = (Board, Castling)> + '_ {
+        std::iter::once((self.start, self.start_castling)).chain(self.entries[..self.ply].iter().map(|e| (e.brett, e.castling)))
+    }
+
+    pub fn moves(&self) -> impl Iterator<Item = ChessMove> + '_ {
+        self.entries.iter().map(|e| e.chess_move)
+    }
+
+    pub fn san_moves(&self) -> impl Iterator<Item = &str> + '_ {
+        self.entries.iter().map(|e| e.san.as_str())
+    }
+
+    /// Plays `chess_move` on the current position. Moves after the current
+    /// ply are discarded, just like in any other chess GUI.
+    pub fn play(&mut self, chess_move: ChessMove) {
+        let (before, castling) = (self.board(), self.castling());
+        let san = chess960::san(&before, castling, chess_move);
+        let (brett, castling) = chess960::play(&before, castling, chess_move);
+        let halfmove_clock = match chess960::is_zeroing(&before, chess_move) {
+            true => 0,
+            false => self.halfmove_clock() + 1,
+        };
+        self.entries.truncate(self.ply);
+        self.entries.push(Entry { chess_move, san, brett, castling, halfmove_clock });
+        self.ply += 1;
+    }
+
+    pub fn undo(&mut self) -> bool {
+        if self.ply == 0 {
+            return false;
+        }
+        self.ply -= 1;
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        if self.at_end() {
+            return false;
+        }
+        self.ply += 1;
+        true
+    }
+
+    pub fn goto(&mut self, ply: usize) {
+        self.ply = ply.min(self.entries.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn play(record: &mut GameRecord, moves: &str) {
+        for m in moves.split_whitespace() {
+            record.play(ChessMove::from_str(m).unwrap());
+        }
+    }
+
+    #[test]
+    fn play_undo_and_redo() {
+        let mut record = GameRecord::new(Board::default(), Castling::none(), 0);
+        play(&mut record, "e2e4 e7e5 g1f3");
+        assert_eq!((record.len(), record.ply()), (3, 3));
+        assert_eq!(record.san_moves().collect::<Vec<_>>(), vec!["e4", "e5", "Nf3"]);
+        assert!(record.undo() && record.undo());
+        assert_eq!(record.board(), Board::default().make_move_new(ChessMove::from_str("e2e4").unwrap()));
+        assert_eq!(record.last_move(), Some(ChessMove::from_str("e2e4").unwrap()));
+        assert_eq!(record.positions().count(), 2);
+        assert!(record.redo());
+        assert!(!record.at_end());
+        // A new move replaces the ones after the current ply
+        play(&mut record, "b1c3");
+        assert_eq!(record.san_moves().collect::<Vec<_>>(), vec!["e4", "e5", "Nc3"]);
+        assert!(record.at_end() && !record.redo());
+        record.goto(0);
+        assert_eq!(record.board(), Board::default());
+        assert!(!record.undo());
+    }
+
+    #[test]
+    fn halfmove_clock() {
+        let start = Board::from_str("4k3/8/8/8/8/8/4P3/R3K3 w - - 0 1").unwrap();
+        let mut record = GameRecord::new(start, Castling::none(), 10);
+        assert_eq!(record.halfmove_clock(), 10);
+        play(&mut record, "a1a2 e8d8");
+        assert_eq!(record.halfmove_clock(), 12);
+        play(&mut record, "e2e4");
+        assert_eq!(record.halfmove_clock(), 0);
+        record.undo();
+        assert_eq!(record.halfmove_clock(), 12);
+        record.goto(0);
+        assert_eq!(record.halfmove_clock(), 10);
+    }
+}
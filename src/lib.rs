@@ -0,0 +1,25 @@
+//! Engine, game state, notation and tablebase without any GUI dependency.
+//! The SDL GUI, the UCI engine and the command line tools in `src/bin` are
+//! built on top of it.
+
+pub mod config;
+pub mod engine;
+pub mod search_stats;
+pub mod tablebase;
+pub mod chess960;
+pub mod convert;
+pub mod game;
+pub mod game_record;
+pub mod clock;
+pub mod time_manager;
+pub mod notation;
+pub mod uci;
+pub mod skill;
+pub mod perft;
+pub mod epd;
+pub mod bench;
+pub mod match_runner;
+pub mod variant;
+pub mod variant_engine;
+pub mod trainer;
+pub mod puzzle;
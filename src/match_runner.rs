@@ -0,0 +1,467 @@
+use chess::Board;
+use chess::ChessMove;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::io::Write;
+use std::process::Child;
+use std::process::ChildStdin;
+use std::process::ChildStdout;
+use std::process::Command;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::thread;
+use std::time::Duration;
+use crate::clock::TimeControl;
+use crate::config::Config;
+use crate::convert;
+use crate::engine::Engine;
+use crate::game;
+use crate::game::Game;
+use crate::game::Outcome;
+use crate::skill::Skill;
+use crate::time_manager::TimeManager;
+
+// Games that get this long are adjudicated as draws
+const MAX_PLIES: usize = 400;
+
+const OPENINGS: [&str; 8] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+    "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+    "rnbqkbnr/ppp1pppp/8/3p4/3P4/8/PPP1PPPP/RNBQKBNR w KQkq - 0 2",
+    "rnbqkb1r/pppppppp/5n2/8/2P5/8/PP1PPPPP/RNBQKBNR w KQkq - 1 2",
+    "rnbqkbnr/pppp1ppp/4p3/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+    "rnbqkbnr/pp1ppppp/2p5/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2",
+    "rnbqkbnr/pppppppp/8/8/8/5N2/PPPPPPPP/RNBQKB1R b KQkq - 1 1",
+];
+
+/// Either this engine with its own settings or an external UCI engine.
+#[derive(Clone, Debug)]
+pub enum PlayerSpec {
+    Internal { skill: Skill, depth: Option<u64> },
+    Uci { command: String },
+}
+
+impl PlayerSpec {
+    /// `schach2[:skill=N,depth=N]` or the command line of a UCI engine.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (name, options) = spec.split_once(':').unwrap_or((spec, ""));
+        if name != "schach2" {
+            return Ok(PlayerSpec::Uci { command: spec.to_string() });
+        }
+        let mut skill = Skill::full();
+        let mut depth = None;
+        for option in options.split(',').filter(|o| !o.is_empty()) {
+            let (key, value) = option.split_once('=').ok_or(format!("Expected key=value: {}", option))?;
+            match key {
+                "skill" => skill = Skill::new(value.parse().map_err(|_| format!("Invalid skill: {}", value))?),
+                "depth" => depth = Some(value.parse().map_err(|_| format!("Invalid depth: {}", value))?),
+                _ => return Err(format!("Unknown option: {}", key)),
+            }
+        }
+        Ok(PlayerSpec::Internal { skill, depth })
+    }
+
+    fn create(&self, config: &Config) -> Result<Box<dyn Player>, String> {
+        match self {
+            PlayerSpec::Internal { skill, depth } => Ok(Box::new(InternalPlayer { skill: *skill, depth: *depth, config: config.clone() })),
+            PlayerSpec::Uci { command } => Ok(Box::new(UciPlayer::start(command)?)),
+        }
+    }
+}
+
+trait Player {
+    // Called before every game, an engine forgets what it learned in the last one
+    fn new_game(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+
+    // `None` if the engine finds no move, the game is over then
+    fn best_move(&mut self, start: &Board, moves: &[ChessMove], brett: &Board, halfmoves: u32, movetime: Duration) -> Result<Option<ChessMove>, String>;
+}
+
+struct InternalPlayer {
+    skill: Skill,
+    depth: Option<u64>,
+    config: Config,
+}
+
+impl Player for InternalPlayer {
+    fn best_move(&mut self, _: &Board, _: &[ChessMove], brett: &Board, halfmoves: u32, movetime: Duration) -> Result<Option<ChessMove>, String> {
+        let mut engine = Engine::new(*brett);
+        engine.set_skill(self.skill);
+        engine.set_tablebase(&self.config.tablebase.path);
+        if let Some(depth) = self.depth {
+            engine.set_depth_limit(depth);
+        }
+        // The game is over before the clock reaches 100, it fits the engine's counter
+        let found = engine.best_move(halfmoves as u8, self.config.engine.depth, self.config.engine.max_depth, TimeManager::fixed(movetime));
+        Ok(found.map(|(chess_move, _, _)| chess_move))
+    }
+}
+
+struct UciPlayer {
+    process: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl UciPlayer {
+    // `command` may carry arguments, e.g. `./schach2-uci`
+    fn start(command: &str) -> Result<Self, String> {
+        let mut parts = command.split_whitespace();
+        let mut process = Command::new(parts.next().ok_or("Empty engine command")?)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("{}: {}", command, e))?;
+        let stdin = process.stdin.take().ok_or("No stdin")?;
+        let stdout = BufReader::new(process.stdout.take().ok_or("No stdout")?);
+        let mut player = UciPlayer { process, stdin, stdout };
+        player.send("uci")?;
+        player.wait_for("uciok")?;
+        player.send("isready")?;
+        player.wait_for("readyok")?;
+        Ok(player)
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{}", command).map_err(|e| e.to_string())
+    }
+
+    // Returns the first line starting with `prefix`
+    fn wait_for(&mut self, prefix: &str) -> Result<String, String> {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if self.stdout.read_line(&mut line).map_err(|e| e.to_string())? == 0 {
+                return Err("Engine closed its output".to_string());
+            }
+            if line.starts_with(prefix) {
+                return Ok(line.trim().to_string());
+            }
+        }
+    }
+}
+
+impl Player for UciPlayer {
+    fn new_game(&mut self) -> Result<(), String> {
+        self.send("ucinewgame")?;
+        self.send("isready")?;
+        self.wait_for("readyok")?;
+        Ok(())
+    }
+
+    fn best_move(&mut self, start: &Board, moves: &[ChessMove], brett: &Board, _: u32, movetime: Duration) -> Result<Option<ChessMove>, String> {
+        let moves: Vec<String> = moves.iter().map(|m| m.to_string()).collect();
+        self.send(&format!("position fen {} moves {}", start, moves.join(" ")))?;
+        self.send(&format!("go movetime {}", movetime.as_millis()))?;
+        let line = self.wait_for("bestmove")?;
+        let uci = line.split_whitespace().nth(1).ok_or(format!("Invalid answer: {}", line))?;
+        if uci == "0000" || uci == "(none)" {
+            return Ok(None);
+        }
+        convert::from_uci(brett, uci).map(Some).ok_or(format!("Illegal move {} in {}", uci, brett))
+    }
+}
+
+impl Drop for UciPlayer {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.process.wait();
+    }
+}
+
+/// Plays one game from `start`, returns the outcome and why the game ended.
+fn play_game(start: &Board, white: &mut dyn Player, black: &mut dyn Player, movetime: Duration) -> Result<(Outcome, &'static str), String> {
+    white.new_game()?;
+    black.new_game()?;
+    let mut game = Game::new(*start, TimeControl::Unlimited, [game::Player::Human; 2]);
+    loop {
+        if let Some((outcome, termination)) = game.result() {
+            return Ok((outcome, termination.name()));
+        }
+        if game.record().len() >= MAX_PLIES {
+            return Ok((Outcome::Draw, "adjudication"));
+        }
+        let brett = game.board();
+        let moves: Vec<ChessMove> = game.record().moves().collect();
+        let halfmoves = game.record().halfmove_clock();
+        let found = match brett.side_to_move() {
+            chess::Color::White => white.best_move(start, &moves, &brett, halfmoves, movetime)?,
+            chess::Color::Black => black.best_move(start, &moves, &brett, halfmoves, movetime)?,
+        };
+        // Mate and stalemate ended the game above, giving up a playable position loses it
+        let Some(chess_move) = found else {
+            return Ok((Outcome::win_for(!brett.side_to_move()), "no move"));
+        };
+        game.play(chess_move)?;
+    }
+}
+
+/// Wins, draws and losses of the first player.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Score {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl Score {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    fn mean(&self) -> f64 {
+        (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64
+    }
+
+    // Variance of the result of a single game
+    fn variance(&self) -> f64 {
+        let mean = self.mean();
+        let n = self.games() as f64;
+        (self.wins as f64 * (1.0 - mean).powi(2) + self.draws as f64 * (0.5 - mean).powi(2) + self.losses as f64 * mean.powi(2)) / n
+    }
+
+    /// Elo difference with the half width of its 95 % confidence interval.
+    /// Infinite without an error bar if every game was won or every game lost.
+    pub fn elo(&self) -> Option<(f64, f64)> {
+        if self.games() == 0 {
+            return None;
+        }
+        let mean = self.mean();
+        if mean <= 0.0 || mean >= 1.0 {
+            return Some((elo_from_score(mean), 0.0));
+        }
+        let error = 1.96 * (self.variance() / self.games() as f64).sqrt();
+        let low = elo_from_score((mean - error).max(0.0));
+        let high = elo_from_score((mean + error).min(1.0));
+        Some((elo_from_score(mean), (high - low) / 2.0))
+    }
+
+    /// Log-likelihood ratio of H1 (`elo1`) against H0 (`elo0`) in the normal approximation.
+    pub fn llr(&self, elo0: f64, elo1: f64) -> f64 {
+        let variance = self.variance();
+        if self.games() == 0 || variance == 0.0 {
+            return 0.0;
+        }
+        let (s0, s1) = (score_from_elo(elo0), score_from_elo(elo1));
+        self.games() as f64 * (s1 - s0) * (2.0 * self.mean() - s0 - s1) / (2.0 * variance)
+    }
+}
+
+fn elo_from_score(score: f64) -> f64 {
+    match score {
+        s if s <= 0.0 => f64::NEG_INFINITY,
+        s if s >= 1.0 => f64::INFINITY,
+        s => -400.0 * (1.0 / s - 1.0).log10(),
+    }
+}
+
+fn score_from_elo(elo: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf(-elo / 400.0))
+}
+
+fn format_elo(elo: f64, error: f64) -> String {
+    match elo {
+        elo if elo == f64::INFINITY => "+inf".to_string(),
+        elo if elo == f64::NEG_INFINITY => "-inf".to_string(),
+        elo => format!("{:.1} +/- {:.1}", elo, error),
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SprtVerdict {
+    AcceptH0,
+    AcceptH1,
+    Continue,
+}
+
+// Bounds for alpha = beta = 0.05
+pub fn sprt_verdict(llr: f64) -> SprtVerdict {
+    let bound = (0.95f64 / 0.05).ln();
+    match llr {
+        llr if llr >= bound => SprtVerdict::AcceptH1,
+        llr if llr <= -bound => SprtVerdict::AcceptH0,
+        _ => SprtVerdict::Continue,
+    }
+}
+
+fn read_openings(path: &str) -> Result<Vec<Board>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let openings = content.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            // EPD lines have no move counters and may carry operations
+            let fields: Vec<&str> = line.split_whitespace().take(4).collect();
+            let fen = format!("{} 0 1", fields.join(" "));
+            Board::from_str(&fen).map_err(|e| format!("Invalid opening {}: {}", line, e))
+        })
+        .collect::<Result<Vec<Board>, String>>()?;
+    if openings.is_empty() {
+        return Err(format!("{}: no openings", path));
+    }
+    Ok(openings)
+}
+
+/// `match <player1> <player2> [--games N] [--concurrency N] [--movetime ms] [--openings file] [--sprt elo0 elo1]`
+pub fn run(args: &[String], config: &Config) -> Result<(), String> {
+    let usage = "Usage: match <player1> <player2> [--games N] [--concurrency N] [--movetime ms] [--openings file] [--sprt elo0 elo1]";
+    if args.len() < 2 {
+        return Err(usage.to_string());
+    }
+    let players = [PlayerSpec::parse(&args[0])?, PlayerSpec::parse(&args[1])?];
+    let value = |name: &str, i: usize| args.iter().position(|a| a == name).and_then(|p| args.get(p + 1 + i));
+    let number = |name: &str, default: u64| -> Result<u64, String> {
+        match value(name, 0) {
+            Some(v) => v.parse().map_err(|_| usage.to_string()),
+            None => Ok(default),
+        }
+    };
+    let games = number("--games", 100)? as usize;
+    let concurrency = number("--concurrency", 2)?.max(1) as usize;
+    let movetime = Duration::from_millis(number("--movetime", 100)?);
+    let openings = match value("--openings", 0) {
+        Some(path) => read_openings(path)?,
+        None => OPENINGS.iter().map(|fen| Board::from_str(fen).unwrap()).collect(),
+    };
+    let sprt = match (value("--sprt", 0), value("--sprt", 1)) {
+        (Some(elo0), Some(elo1)) => Some((elo0.parse::<f64>().map_err(|_| usage)?, elo1.parse::<f64>().map_err(|_| usage)?)),
+        _ => None,
+    };
+
+    let next_game = AtomicUsize::new(0);
+    let score = Mutex::new(Score::default());
+    let error: Mutex<Option<String>> = Mutex::new(None);
+    thread::scope(|s| {
+        for _ in 0..concurrency {
+            s.spawn(|| {
+                let mut created = (players[0].create(config), players[1].create(config));
+                let (first, second) = match (&mut created.0, &mut created.1) {
+                    (Ok(first), Ok(second)) => (first, second),
+                    (Err(e), _) | (_, Err(e)) => {
+                        *error.lock().unwrap() = Some(e.clone());
+                        return;
+                    },
+                };
+                loop {
+                    let game = next_game.fetch_add(1, Ordering::Relaxed);
+                    if game >= games || error.lock().unwrap().is_some() {
+                        break;
+                    }
+                    // Every opening is played twice, the first player has White in the even games
+                    let start = openings[game / 2 % openings.len()];
+                    let first_is_white = game.is_multiple_of(2);
+                    let result = match first_is_white {
+                        true => play_game(&start, first.as_mut(), second.as_mut(), movetime),
+                        false => play_game(&start, second.as_mut(), first.as_mut(), movetime),
+                    };
+                    let (outcome, reason) = match result {
+                        Ok(result) => result,
+                        Err(e) => {
+                            *error.lock().unwrap() = Some(e);
+                            break;
+                        },
+                    };
+                    let mut score = score.lock().unwrap();
+                    match (outcome, first_is_white) {
+                        (Outcome::Draw, _) => score.draws += 1,
+                        (Outcome::WhiteWins, true) | (Outcome::BlackWins, false) => score.wins += 1,
+                        _ => score.losses += 1,
+                    }
+                    let result = match outcome {
+                        Outcome::WhiteWins => "1-0",
+                        Outcome::BlackWins => "0-1",
+                        Outcome::Draw => "1/2-1/2",
+                    };
+                    println!("Game {} ({} as {}): {} by {}  +{} ={} -{}", game + 1, args[0], if first_is_white { "White" } else { "Black" }, result, reason.to_lowercase(), score.wins, score.draws, score.losses);
+                    if let Some((elo0, elo1)) = sprt {
+                        if sprt_verdict(score.llr(elo0, elo1)) != SprtVerdict::Continue {
+                            // No new games are started, running ones are finished
+                            next_game.store(games, Ordering::Relaxed);
+                        }
+                    }
+                }
+            });
+        }
+    });
+    if let Some(e) = error.into_inner().unwrap() {
+        return Err(e);
+    }
+
+    let score = score.into_inner().unwrap();
+    println!();
+    println!("{} vs {}: +{} ={} -{} in {} games", args[0], args[1], score.wins, score.draws, score.losses, score.games());
+    if let Some((elo, error)) = score.elo() {
+        println!("Elo: {}", format_elo(elo, error));
+    }
+    if let Some((elo0, elo1)) = sprt {
+        let llr = score.llr(elo0, elo1);
+        let verdict = match sprt_verdict(llr) {
+            SprtVerdict::AcceptH1 => "H1 accepted",
+            SprtVerdict::AcceptH0 => "H0 accepted",
+            SprtVerdict::Continue => "inconclusive",
+        };
+        println!("SPRT [{}, {}]: LLR {:.2} ({:.2}, {:.2}), {}", elo0, elo1, llr, -(0.95f64 / 0.05).ln(), (0.95f64 / 0.05).ln(), verdict);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: f64, expected: f64) -> bool {
+        (actual - expected).abs() < 0.01
+    }
+
+    #[test]
+    fn elo_with_error_bars() {
+        let (elo, error) = Score { wins: 60, draws: 0, losses: 40 }.elo().unwrap();
+        assert!(close(elo, 70.44) && close(error, 70.57), "{} {}", elo, error);
+        let (elo, error) = Score { wins: 300, draws: 400, losses: 300 }.elo().unwrap();
+        assert!(close(elo, 0.0) && close(error, 16.69), "{} {}", elo, error);
+        assert_eq!(Score::default().elo(), None);
+        assert_eq!(Score { wins: 3, draws: 0, losses: 0 }.elo(), Some((f64::INFINITY, 0.0)));
+        assert_eq!(Score { wins: 0, draws: 0, losses: 2 }.elo(), Some((f64::NEG_INFINITY, 0.0)));
+    }
+
+    #[test]
+    fn elo_output() {
+        let elo = |score: Score| score.elo().map(|(elo, error)| format_elo(elo, error));
+        assert_eq!(elo(Score { wins: 3, draws: 0, losses: 0 }).unwrap(), "+inf");
+        assert_eq!(elo(Score { wins: 0, draws: 0, losses: 2 }).unwrap(), "-inf");
+        assert_eq!(elo(Score { wins: 60, draws: 0, losses: 40 }).unwrap(), "70.4 +/- 70.6");
+    }
+
+    #[test]
+    fn sprt() {
+        let llr = Score { wins: 60, draws: 0, losses: 40 }.llr(0.0, 10.0);
+        assert!(close(llr, 0.556), "{}", llr);
+        assert_eq!(sprt_verdict(llr), SprtVerdict::Continue);
+        let llr = Score { wins: 520, draws: 300, losses: 180 }.llr(0.0, 5.0);
+        assert!(close(llr, 8.195), "{}", llr);
+        assert_eq!(sprt_verdict(llr), SprtVerdict::AcceptH1);
+        assert_eq!(sprt_verdict(-llr), SprtVerdict::AcceptH0);
+        // The bounds are ln(19) for alpha = beta = 0.05
+        assert_eq!(sprt_verdict(2.94), SprtVerdict::Continue);
+        assert_eq!(sprt_verdict(2.95), SprtVerdict::AcceptH1);
+        assert_eq!(Score { wins: 5, draws: 0, losses: 0 }.llr(0.0, 10.0), 0.0);
+    }
+
+    #[test]
+    fn openings_file() {
+        let path = std::env::temp_dir().join(format!("schach2-openings-{}.epd", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "\n  \n").unwrap();
+        assert!(read_openings(path).unwrap_err().contains("no openings"));
+        std::fs::write(path, "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - id \"e4\";\n").unwrap();
+        assert_eq!(read_openings(path).unwrap().len(), 1);
+        std::fs::remove_file(path).unwrap();
+    }
+}
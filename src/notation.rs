@@ -0,0 +1,281 @@
+use chess::Board;
+use chess::BoardStatus;
+use chess::ChessMove;
+use chess::MoveGen;
+use chess::Piece;
+use chess::Square;
+
+fn piece_letter(piece: Piece) -> char {
+    match piece {
+        Piece::Pawn => 'P',
+        Piece::Knight => 'N',
+        Piece::Bishop => 'B',
+        Piece::Rook => 'R',
+        Piece::Queen => 'Q',
+        Piece::King => 'K',
+    }
+}
+
+fn piece_from_letter(letter: char) -> Option<Piece> {
+    match letter {
+        'N' => Some(Piece::Knight),
+        'B' => Some(Piece::Bishop),
+        'R' => Some(Piece::Rook),
+        'Q' => Some(Piece::Queen),
+        'K' => Some(Piece::King),
+        _ => None,
+    }
+}
+
+// The chess crate plays castling as a king move by two files
+fn is_castling(chess_move: ChessMove, brett: &Board) -> bool {
+    brett.piece_on(chess_move.get_source()) == Some(Piece::King)
+        && chess_move.get_source().get_file().to_index().abs_diff(chess_move.get_dest().get_file().to_index()) == 2
+}
+
+/// Standard algebraic notation of a legal move, e.g. `Nbd7`, `exd6`, `e8=Q+`
+/// or `O-O#`. Illegal moves are written in UCI notation.
+pub fn san(input: ChessMove, brett: &Board) -> String {
+    if !brett.legal(input) {
+        return input.to_string();
+    }
+    let source = input.get_source();
+    let dest = input.get_dest();
+    let piece = brett.piece_on(source).unwrap();
+    let mut text = String::new();
+
+    if is_castling(input, brett) {
+        text.push_str(if dest.get_file() == chess::File::G { "O-O" } else { "O-O-O" });
+    } else {
+        let capture = brett.piece_on(dest).is_some() || (piece == Piece::Pawn && source.get_file() != dest.get_file());
+        if piece == Piece::Pawn {
+            if capture {
+                text.push(source.to_string().chars().next().unwrap());
+            }
+        } else {
+            text.push(piece_letter(piece));
+            // Only as much of the source square as needed to tell the pieces apart
+            let others: Vec<Square> = MoveGen::new_legal(brett)
+                .filter(|m| m.get_dest() == dest && m.get_source() != source && brett.piece_on(m.get_source()) == Some(piece))
+                .map(|m| m.get_source())
+                .collect();
+            let square = source.to_string();
+            if others.iter().all(|o| o.get_file() != source.get_file()) {
+                text.push_str(if others.is_empty() { "" } else { &square[..1] });
+            } else if others.iter().all(|o| o.get_rank() != source.get_rank()) {
+                text.push_str(&square[1..]);
+            } else {
+                text.push_str(&square);
+            }
+        }
+        if capture {
+            text.push('x');
+        }
+        text.push_str(&dest.to_string());
+        if let Some(promotion) = input.get_promotion() {
+            text.push('=');
+            text.push(piece_letter(promotion));
+        }
+    }
+
+    let after = brett.make_move_new(input);
+    if after.status() == BoardStatus::Checkmate {
+        text.push('#');
+    } else if after.checkers().popcnt() > 0 {
+        text.push('+');
+    }
+    text
+}
+
+/// Parses SAN as found in PGN and EPD files. Check marks, annotations, `e.p.`,
+/// castling with zeros and promotions without `=` are accepted, the move has
+/// to be legal and unambiguous.
+pub fn parse_san(brett: &Board, text: &str) -> Result<ChessMove, String> {
+    let stripped = text.trim()
+        .trim_end_matches(['+', '#', '!', '?'])
+        .trim_end_matches("e.p.")
+        .trim_end();
+    let legal = MoveGen::new_legal(brett);
+
+    let candidates: Vec<ChessMove> = match stripped.replace('0', "O").as_str() {
+        "O-O" => legal.filter(|m| is_castling(*m, brett) && m.get_dest().get_file() == chess::File::G).collect(),
+        "O-O-O" => legal.filter(|m| is_castling(*m, brett) && m.get_dest().get_file() == chess::File::C).collect(),
+        _ => {
+            let pattern = SanPattern::parse(stripped).ok_or(format!("Invalid move {}", text))?;
+            legal.filter(|m| pattern.matches(*m, brett)).collect()
+        },
+    };
+    match candidates.as_slice() {
+        [chess_move] => Ok(*chess_move),
+        [] => Err(format!("Illegal move {} in {}", text, brett)),
+        _ => Err(format!("Ambiguous move {} in {}", text, brett)),
+    }
+}
+
+// Everything a SAN move can say about the move, except castling
+struct SanPattern {
+    piece: Piece,
+    file: Option<chess::File>,
+    rank: Option<chess::Rank>,
+    dest: Square,
+    promotion: Option<Piece>,
+}
+
+impl SanPattern {
+    fn parse(text: &str) -> Option<Self> {
+        let mut chars: Vec<char> = text.chars().collect();
+        let piece = match chars.first().copied().and_then(piece_from_letter) {
+            Some(piece) => {
+                chars.remove(0);
+                piece
+            },
+            None => Piece::Pawn,
+        };
+        let promotion = match chars.last().copied().and_then(piece_from_letter) {
+            Some(promotion) => {
+                chars.pop();
+                if chars.last() == Some(&'=') {
+                    chars.pop();
+                }
+                Some(promotion)
+            },
+            None => None,
+        };
+        if chars.len() < 2 {
+            return None;
+        }
+        let dest = square(chars[chars.len() - 2], chars[chars.len() - 1])?;
+        chars.truncate(chars.len() - 2);
+        if matches!(chars.last(), Some('x' | ':' | '-')) {
+            chars.pop();
+        }
+
+        let (mut file, mut rank) = (None, None);
+        for c in chars {
+            match c {
+                'a'..='h' if file.is_none() && rank.is_none() => file = Some(chess::File::from_index(c as usize - 'a' as usize)),
+                '1'..='8' if rank.is_none() => rank = Some(chess::Rank::from_index(c as usize - '1' as usize)),
+                _ => return None,
+            }
+        }
+        Some(SanPattern { piece, file, rank, dest, promotion })
+    }
+
+    fn matches(&self, chess_move: ChessMove, brett: &Board) -> bool {
+        let source = chess_move.get_source();
+        brett.piece_on(source) == Some(self.piece)
+            && chess_move.get_dest() == self.dest
+            && chess_move.get_promotion() == self.promotion
+            && self.file.is_none_or(|file| source.get_file() == file)
+            && self.rank.is_none_or(|rank| source.get_rank() == rank)
+            && !is_castling(chess_move, brett)
+    }
+}
+
+fn square(file: char, rank: char) -> Option<Square> {
+    match (file, rank) {
+        ('a'..='h', '1'..='8') => Some(Square::make_square(
+            chess::Rank::from_index(rank as usize - '1' as usize),
+            chess::File::from_index(file as usize - 'a' as usize),
+        )),
+        _ => None,
+    }
+}
+
+// Stops at the first move that isn't legal, e.g. a line belonging to another position
+pub fn san_line(brett: &Board, line: &[ChessMove]) -> String {
+    let mut brett = *brett;
+    let mut sans = Vec::new();
+    for m in line {
+        if !brett.legal(*m) {
+            break;
+        }
+        sans.push(san(*m, &brett));
+        brett = brett.make_move_new(*m);
+    }
+    sans.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn board(fen: &str) -> Board {
+        Board::from_str(fen).unwrap()
+    }
+
+    fn uci(text: &str) -> ChessMove {
+        ChessMove::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn writes_standard_notation() {
+        let cases = [
+            // Two knights can reach d7
+            ("rnbqkb1r/ppp1pppp/5n2/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1", "b8d7", "Nbd7"),
+            ("4k3/8/8/8/8/8/8/R4RK1 w - - 0 1", "a1d1", "Rad1"),
+            ("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3", "R1a3"),
+            ("4k3/8/8/8/8/Q1Q5/8/Q3K3 w - - 0 1", "a3b2", "Qa3b2"),
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", "exd6"),
+            ("k7/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7e8q", "e8=Q+"),
+            ("3r3k/4P3/8/8/8/8/8/4K3 w - - 0 1", "e7d8n", "exd8=N"),
+            ("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1", "e1c1", "O-O-O"),
+            ("4k3/8/8/8/8/8/8/4K2R w K - 0 1", "e1g1", "O-O"),
+            ("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2", "d8h4", "Qh4#"),
+        ];
+        for (fen, m, expected) in cases {
+            assert_eq!(san(uci(m), &board(fen)), expected, "{}", fen);
+            assert_eq!(parse_san(&board(fen), expected), Ok(uci(m)), "{}", fen);
+        }
+    }
+
+    #[test]
+    fn castling_into_mate() {
+        let brett = board("4rkr1/4p1p1/8/8/8/8/4P3/4K2R w K - 0 1");
+        assert_eq!(san(uci("e1g1"), &brett), "O-O#");
+        assert_eq!(parse_san(&brett, "O-O#"), Ok(uci("e1g1")));
+    }
+
+    #[test]
+    fn agrees_with_shakmaty() {
+        let positions = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ];
+        for fen in positions {
+            let brett = board(fen);
+            let pos = crate::convert::to_shakmaty(&brett, 0).unwrap();
+            for m in MoveGen::new_legal(&brett) {
+                let reference = shakmaty::san::SanPlus::from_move(pos.clone(), &crate::convert::to_shakmaty_move(&pos, m).unwrap());
+                assert_eq!(san(m, &brett), reference.to_string(), "{} in {}", m, fen);
+                assert_eq!(parse_san(&brett, &san(m, &brett)), Ok(m));
+            }
+        }
+    }
+
+    #[test]
+    fn parses_lenient_input() {
+        let brett = board("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        assert_eq!(parse_san(&brett, "0-0"), Ok(uci("e1g1")));
+        assert_eq!(parse_san(&brett, "O-O-O!?"), Ok(uci("e1c1")));
+        assert_eq!(parse_san(&brett, "Nxf7"), Ok(uci("e5f7")));
+        assert_eq!(parse_san(&brett, "Nf7"), Ok(uci("e5f7")));
+        assert_eq!(parse_san(&brett, "Ne5xf7+"), Ok(uci("e5f7")));
+        assert_eq!(parse_san(&board("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1"), "exd6 e.p."), Ok(uci("e5d6")));
+        assert_eq!(parse_san(&board("k7/4P3/8/8/8/8/8/4K3 w - - 0 1"), "e8Q"), Ok(uci("e7e8q")));
+    }
+
+    #[test]
+    fn rejects_illegal_and_ambiguous_moves() {
+        let brett = board("rnbqkb1r/ppp1pppp/5n2/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1");
+        assert!(parse_san(&brett, "Nd7").unwrap_err().starts_with("Ambiguous"));
+        assert!(parse_san(&brett, "Ke7").unwrap_err().starts_with("Illegal"));
+        assert!(parse_san(&brett, "O-O").unwrap_err().starts_with("Illegal"));
+        assert!(parse_san(&brett, "Zz9").unwrap_err().starts_with("Invalid"));
+        assert!(parse_san(&board("k7/4P3/8/8/8/8/8/4K3 w - - 0 1"), "e8").is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use chess::Board;
+use chess::ChessMove;
+use chess::MoveGen;
+use shakmaty::Position;
+use std::str::FromStr;
+use std::time::Instant;
+use crate::convert;
+
+/// Number of leaf nodes `depth` plies below `brett`.
+pub fn perft(brett: &Board, depth: u32) -> u64 {
+    match depth {
+        0 => 1,
+        1 => MoveGen::new_legal(brett).len() as u64,
+        _ => MoveGen::new_legal(brett).map(|m| perft(&brett.make_move_new(m), depth - 1)).sum(),
+    }
+}
+
+/// Like `perft`, but the moves are generated by shakmaty on the converted
+/// position and converted back. Moves that don't convert to a legal move of
+/// the chess crate are not counted, so any conversion error changes the result.
+pub fn perft_converted(brett: &Board, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let pos = match convert::to_shakmaty(brett, 0) {
+        Some(pos) => pos,
+        None => return 0,
+    };
+    pos.legal_moves().iter()
+        .filter_map(convert::from_shakmaty_move)
+        .filter(|m| brett.legal(*m))
+        .map(|m| perft_converted(&brett.make_move_new(m), depth - 1))
+        .sum()
+}
+
+pub fn divide(brett: &Board, depth: u32, converted: bool) -> Vec<(ChessMove, u64)> {
+    let count = if converted { perft_converted } else { perft };
+    let mut moves: Vec<(ChessMove, u64)> = MoveGen::new_legal(brett)
+        .map(|m| (m, count(&brett.make_move_new(m), depth.saturating_sub(1))))
+        .collect();
+    moves.sort_by_key(|(m, _)| m.to_string());
+    moves
+}
+
+/// `perft <depth> [--converted] [fen]` and `divide <depth> [--converted] [fen]`
+pub fn run(command: &str, args: &[String]) -> Result<(), String> {
+    let depth = args.first()
+        .and_then(|d| d.parse::<u32>().ok())
+        .ok_or(format!("Usage: {} <depth> [--converted] [fen]", command))?;
+    let converted = args.iter().any(|a| a == "--converted");
+    let fen = args[1..].iter().filter(|a| *a != "--converted").cloned().collect::<Vec<String>>().join(" ");
+    let brett = match fen.is_empty() {
+        true => Board::default(),
+        false => Board::from_str(&fen).map_err(|e| format!("Invalid FEN {}: {}", fen, e))?,
+    };
+
+    let start = Instant::now();
+    let nodes = if command == "divide" {
+        let moves = divide(&brett, depth, converted);
+        for (m, nodes) in &moves {
+            println!("{}: {}", m, nodes);
+        }
+        moves.iter().map(|(_, nodes)| nodes).sum()
+    } else if converted {
+        perft_converted(&brett, depth)
+    } else {
+        perft(&brett, depth)
+    };
+    let time = start.elapsed();
+    println!("Nodes: {}", nodes);
+    println!("Time: {:.3} s ({:.0} kN/s)", time.as_secs_f64(), nodes as f64 / time.as_secs_f64().max(0.001) / 1000.0);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Standard perft positions with their node counts per depth
+    const POSITIONS: [(&str, &[u64]); 8] = [
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &[20, 400, 8902, 197281]),
+        // Kiwipete
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862]),
+        // En passant, discovered checks along the rank
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238]),
+        // Promotions and castling rights lost by captured rooks
+        ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467]),
+        ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379]),
+        ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2079, 89890]),
+        // En passant captures that would leave the king in check
+        ("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", &[15, 126, 1928, 13931]),
+        ("8/5bk1/8/2Pp4/8/1K6/8/8 w - d6 0 1", &[8, 104, 736, 9287]),
+    ];
+
+    #[test]
+    fn perft_chess_board() {
+        for (fen, counts) in POSITIONS {
+            let brett = Board::from_str(fen).unwrap();
+            for (depth, count) in counts.iter().enumerate() {
+                assert_eq!(perft(&brett, depth as u32 + 1), *count, "{} depth {}", fen, depth + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn perft_shakmaty_conversion() {
+        for (fen, counts) in POSITIONS {
+            let brett = Board::from_str(fen).unwrap();
+            // The conversion is slow, the last depth takes too long for a test
+            for (depth, count) in counts.iter().enumerate().take(3) {
+                assert_eq!(perft_converted(&brett, depth as u32 + 1), *count, "{} depth {}", fen, depth + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn divide_sums_up_to_perft() {
+        let brett = Board::from_str(POSITIONS[1].0).unwrap();
+        let moves = divide(&brett, 3, false);
+        assert_eq!(moves.len(), 48);
+        assert_eq!(moves.iter().map(|(_, nodes)| nodes).sum::<u64>(), 97862);
+        assert_eq!(divide(&brett, 3, true), moves);
+    }
+}
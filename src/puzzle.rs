@@ -0,0 +1,202 @@
+//! Tactics puzzles from Lichess puzzle CSV files or EPD suites with `bm`,
+//! the checking of the solver's moves and the score of a session.
+
+use chess::Board;
+use chess::BoardStatus;
+use chess::ChessMove;
+use std::str::FromStr;
+use crate::convert;
+use crate::epd::EpdPosition;
+
+#[derive(Clone)]
+pub struct Puzzle {
+    pub id: String,
+    // Position before the opponent's move that sets up the puzzle
+    pub start: Board,
+    pub setup: Option<ChessMove>,
+    // Moves of the solver at even, replies of the opponent at odd indices
+    pub solution: Vec<ChessMove>,
+    // Other first moves that also solve it, from several `bm` in EPD
+    pub alternatives: Vec<ChessMove>,
+    pub rating: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Verdict {
+    // Right so far, the opponent replies with the next move of the solution
+    Correct,
+    Solved,
+    Wrong,
+}
+
+impl Puzzle {
+    /// `PuzzleId,FEN,Moves,Rating,...` where the first of the UCI moves is
+    /// the opponent's and the FEN the position before it.
+    pub fn from_lichess_csv(line: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = line.split(',').collect();
+        if fields.len() < 3 {
+            return Err(format!("Not a Lichess puzzle: {}", line));
+        }
+        let start = Board::from_str(fields[1]).map_err(|e| format!("Invalid position {}: {}", fields[1], e))?;
+        let mut brett = start;
+        let mut moves = Vec::new();
+        for uci in fields[2].split_whitespace() {
+            let chess_move = convert::from_uci(&brett, uci).ok_or(format!("Illegal move {} in puzzle {}", uci, fields[0]))?;
+            brett = brett.make_move_new(chess_move);
+            moves.push(chess_move);
+        }
+        if moves.len() < 2 {
+            return Err(format!("Puzzle {} has no solution", fields[0]));
+        }
+        Ok(Puzzle {
+            id: fields[0].to_string(),
+            start,
+            setup: Some(moves.remove(0)),
+            solution: moves,
+            alternatives: Vec::new(),
+            rating: fields.get(3).and_then(|r| r.parse().ok()),
+        })
+    }
+
+    /// One move puzzle, every `bm` is a solution.
+    pub fn from_epd(line: &str) -> Result<Self, String> {
+        let position = EpdPosition::parse(line)?;
+        let (first, others) = position.best_moves.split_first().ok_or(format!("No bm in {}", line))?;
+        Ok(Puzzle {
+            id: position.id,
+            start: position.brett,
+            setup: None,
+            solution: vec![*first],
+            alternatives: others.to_vec(),
+            rating: None,
+        })
+    }
+
+    /// The position the solver has to find the first move in.
+    pub fn position(&self) -> Board {
+        match self.setup {
+            Some(setup) => self.start.make_move_new(setup),
+            None => self.start,
+        }
+    }
+
+    /// Checks the solver's `chess_move` in `brett`, the position after the
+    /// first `ply` moves of the solution. Any mate solves the puzzle.
+    pub fn check(&self, ply: usize, brett: &Board, chess_move: ChessMove) -> Verdict {
+        let expected = self.solution.get(ply) == Some(&chess_move) || (ply == 0 && self.alternatives.contains(&chess_move));
+        if expected && ply + 1 >= self.solution.len() {
+            Verdict::Solved
+        } else if expected {
+            Verdict::Correct
+        } else if brett.legal(chess_move) && brett.make_move_new(chess_move).status() == BoardStatus::Checkmate {
+            Verdict::Solved
+        } else {
+            Verdict::Wrong
+        }
+    }
+}
+
+/// Reads `.csv` files in the Lichess format, everything else as EPD.
+pub fn load(path: &str) -> Result<Vec<Puzzle>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let parse = match path.ends_with(".csv") {
+        true => Puzzle::from_lichess_csv,
+        false => Puzzle::from_epd,
+    };
+    let puzzles = content.lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with("PuzzleId"))
+        .map(parse)
+        .collect::<Result<Vec<Puzzle>, String>>()?;
+    if puzzles.is_empty() {
+        return Err(format!("{}: no puzzles", path));
+    }
+    Ok(puzzles)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Score {
+    pub solved: u32,
+    pub failed: u32,
+    pub streak: u32,
+    pub best_streak: u32,
+}
+
+impl Score {
+    pub fn record(&mut self, solved: bool) {
+        if solved {
+            self.solved += 1;
+            self.streak += 1;
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.failed += 1;
+            self.streak = 0;
+        }
+    }
+}
+
+/// The puzzles of a file in order, starting over after the last one.
+pub struct Session {
+    puzzles: Vec<Puzzle>,
+    next: usize,
+    pub score: Score,
+}
+
+impl Session {
+    pub fn new(puzzles: Vec<Puzzle>) -> Self {
+        Session { puzzles, next: 0, score: Score::default() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.puzzles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.puzzles.is_empty()
+    }
+
+    /// The next puzzle and its number, counted from one.
+    pub fn next_puzzle(&mut self) -> (usize, &Puzzle) {
+        let index = self.next % self.puzzles.len();
+        self.next = index + 1;
+        (index + 1, &self.puzzles[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn uci(brett: &Board, m: &str) -> ChessMove {
+        convert::from_uci(brett, m).unwrap()
+    }
+
+    #[test]
+    fn lichess_puzzle_with_alternative_mate() {
+        let line = "t3st,6k1/5ppp/8/8/8/8/5PPP/R3R1K1 b - - 0 1,g8h8 a1a8,1234,75,90,100,mate mateIn1,https://lichess.org/abc,";
+        let puzzle = Puzzle::from_lichess_csv(line).unwrap();
+        assert_eq!(puzzle.rating, Some(1234));
+        let brett = puzzle.position();
+        assert_eq!(brett.side_to_move(), chess::Color::White);
+        assert_eq!(puzzle.check(0, &brett, uci(&brett, "a1a8")), Verdict::Solved);
+        assert_eq!(puzzle.check(0, &brett, uci(&brett, "e1e8")), Verdict::Solved);
+        assert_eq!(puzzle.check(0, &brett, uci(&brett, "a1b1")), Verdict::Wrong);
+        assert!(Puzzle::from_lichess_csv("t3st,6k1/5ppp/8/8/8/8/5PPP/R3R1K1 b - - 0 1,g8h8 a1a9").is_err());
+    }
+
+    #[test]
+    fn epd_puzzle_and_score() {
+        let puzzle = Puzzle::from_epd("r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - bm Qxf7+; id \"scholar\";").unwrap();
+        let brett = puzzle.position();
+        assert_eq!(puzzle.id, "scholar");
+        assert_eq!(puzzle.check(0, &brett, uci(&brett, "f3f7")), Verdict::Solved);
+        assert_eq!(puzzle.check(0, &brett, uci(&brett, "c4f7")), Verdict::Wrong);
+
+        let mut session = Session::new(vec![puzzle]);
+        assert_eq!(session.next_puzzle().0, 1);
+        assert_eq!(session.next_puzzle().0, 1);
+        for solved in [true, true, false, true] {
+            session.score.record(solved);
+        }
+        assert_eq!(session.score, Score { solved: 3, failed: 1, streak: 1, best_streak: 2 });
+    }
+}
@@ -0,0 +1,79 @@
+//! Counters of the search for `go` info lines, the bench and the analysis
+//! panel. There are no null-move successes among them, the engine has no
+//! null-move pruning to count.
+
+use std::fmt;
+use std::ops::AddAssign;
+
+/// Counters collected by the search, summed over all root moves.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SearchStats {
+    pub nodes: u64,
+    // Nodes below the nominal depth, reached through the capture and check extension
+    pub qnodes: u64,
+    pub tt_probes: u64,
+    pub tt_hits: u64,
+    pub cutoffs: u64,
+    pub first_move_cutoffs: u64,
+    pub seldepth: u64,
+}
+
+impl SearchStats {
+    pub fn tt_hit_rate(&self) -> f64 {
+        ratio(self.tt_hits, self.tt_probes)
+    }
+
+    // Good move ordering finds most cut-offs with the first move
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        ratio(self.first_move_cutoffs, self.cutoffs)
+    }
+}
+
+fn ratio(part: u64, total: u64) -> f64 {
+    match total {
+        0 => 0.0,
+        total => part as f64 / total as f64,
+    }
+}
+
+impl AddAssign for SearchStats {
+    fn add_assign(&mut self, other: SearchStats) {
+        self.nodes += other.nodes;
+        self.qnodes += other.qnodes;
+        self.tt_probes += other.tt_probes;
+        self.tt_hits += other.tt_hits;
+        self.cutoffs += other.cutoffs;
+        self.first_move_cutoffs += other.first_move_cutoffs;
+        self.seldepth = self.seldepth.max(other.seldepth);
+    }
+}
+
+impl fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "nodes {} qnodes {} ({:.1} %) tt {}/{} ({:.1} %) cutoffs {} first move {:.1} % seldepth {}",
+            self.nodes, self.qnodes, ratio(self.qnodes, self.nodes) * 100.0,
+            self.tt_hits, self.tt_probes, self.tt_hit_rate() * 100.0,
+            self.cutoffs, self.first_move_cutoff_rate() * 100.0, self.seldepth,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accumulation() {
+        let mut stats = SearchStats::default();
+        assert_eq!((stats.tt_hit_rate(), stats.first_move_cutoff_rate()), (0.0, 0.0));
+        stats += SearchStats { nodes: 100, qnodes: 40, tt_probes: 50, tt_hits: 10, cutoffs: 20, first_move_cutoffs: 15, seldepth: 9 };
+        stats += SearchStats { nodes: 300, qnodes: 60, tt_probes: 150, tt_hits: 40, cutoffs: 20, first_move_cutoffs: 17, seldepth: 7 };
+        // Counters add up, the selective depth is the deepest of all
+        assert_eq!(stats, SearchStats { nodes: 400, qnodes: 100, tt_probes: 200, tt_hits: 50, cutoffs: 40, first_move_cutoffs: 32, seldepth: 9 });
+        assert_eq!(stats.tt_hit_rate(), 0.25);
+        assert_eq!(stats.first_move_cutoff_rate(), 0.8);
+        assert_eq!(stats.to_string(), "nodes 400 qnodes 100 (25.0 %) tt 50/200 (25.0 %) cutoffs 40 first move 80.0 % seldepth 9");
+    }
+}
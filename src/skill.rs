@@ -0,0 +1,131 @@
+use rand::Rng;
+
+pub const MAX_LEVEL: u8 = 20;
+pub const MIN_ELO: u32 = 800;
+pub const MAX_ELO: u32 = 2400;
+
+/// Playing strength from 0 to `MAX_LEVEL`. Below the maximum the engine
+/// searches less, picks among the near-best moves and now and then plays
+/// the move of a shallow iteration that misses a tactic.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Skill {
+    level: u8,
+}
+
+impl Skill {
+    pub fn new(level: u8) -> Self {
+        Skill { level: level.min(MAX_LEVEL) }
+    }
+
+    pub fn full() -> Self {
+        Skill::new(MAX_LEVEL)
+    }
+
+    // Linear between MIN_ELO at level 0 and MAX_ELO at the maximum
+    pub fn from_elo(elo: u32) -> Self {
+        let elo = elo.clamp(MIN_ELO, MAX_ELO);
+        Skill::new(((elo - MIN_ELO) * MAX_LEVEL as u32 / (MAX_ELO - MIN_ELO)) as u8)
+    }
+
+    pub fn level(&self) -> u8 {
+        self.level
+    }
+
+    pub fn elo(&self) -> u32 {
+        MIN_ELO + self.level as u32 * (MAX_ELO - MIN_ELO) / MAX_LEVEL as u32
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.level == MAX_LEVEL
+    }
+
+    fn weakness(&self) -> u8 {
+        MAX_LEVEL - self.level
+    }
+
+    // Upper bounds for `depth` and `max_depth` of the iterative deepening
+    pub fn depth_limit(&self) -> Option<(u64, u64)> {
+        match self.is_full() {
+            true => None,
+            false => Some((1 + self.level as u64 / 4, 4 + self.level as u64 / 2)),
+        }
+    }
+
+    // No new iteration is started after this many nodes
+    pub fn node_limit(&self) -> Option<u64> {
+        match self.is_full() {
+            true => None,
+            false => Some(2000 << (self.level / 2)),
+        }
+    }
+
+    // Root moves that get an exact score to choose from
+    pub fn candidates(&self) -> usize {
+        match self.is_full() {
+            true => 1,
+            false => 4,
+        }
+    }
+
+    // Probability of playing from the first, shallow iteration
+    pub fn blunder_chance(&self) -> f64 {
+        self.weakness() as f64 / 40.0
+    }
+
+    /// Picks one of the `scores` (side to move, in pawns, best first). The
+    /// chance of a move falls off exponentially with its distance to the best,
+    /// the temperature grows the weaker the level.
+    pub fn choose(&self, scores: &[f32], rng: &mut impl Rng) -> usize {
+        if self.is_full() || scores.len() < 2 {
+            return 0;
+        }
+        let temperature = self.weakness() as f32 * 0.05;
+        let weights: Vec<f32> = scores.iter().map(|s| ((s - scores[0]) / temperature).exp()).collect();
+        let mut pick = rng.gen::<f32>() * weights.iter().sum::<f32>();
+        for (i, weight) in weights.iter().enumerate() {
+            if pick < *weight {
+                return i;
+            }
+            pick -= weight;
+        }
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn levels_and_elo() {
+        assert_eq!(Skill::new(30), Skill::full());
+        assert_eq!(Skill::from_elo(MIN_ELO).level(), 0);
+        assert_eq!(Skill::from_elo(1600).level(), 10);
+        assert_eq!(Skill::from_elo(5000), Skill::full());
+        assert_eq!(Skill::from_elo(100).level(), 0);
+        for level in 0..=MAX_LEVEL {
+            assert_eq!(Skill::from_elo(Skill::new(level).elo()).level(), level);
+        }
+        assert_eq!(Skill::new(0).depth_limit(), Some((1, 4)));
+        assert_eq!(Skill::new(0).node_limit(), Some(2000));
+        assert_eq!(Skill::full().depth_limit(), None);
+        assert_eq!(Skill::full().blunder_chance(), 0.0);
+    }
+
+    #[test]
+    fn spread_of_choose() {
+        let scores = [0.5, 0.45, 0.0, -3.0];
+        let mut rng = StdRng::seed_from_u64(7);
+        assert!((0..1000).all(|_| Skill::full().choose(&scores, &mut rng) == 0));
+
+        // At level 0 close moves are about as likely as the best, a blunder of three pawns almost never
+        let mut counts = [0; 4];
+        for _ in 0..1000 {
+            counts[Skill::new(0).choose(&scores, &mut rng)] += 1;
+        }
+        assert!(counts[0] > 300 && counts[1] > 280 && counts[2] > 170 && counts[3] < 40, "{:?}", counts);
+        assert_eq!(Skill::new(0).choose(&scores[..1], &mut rng), 0);
+    }
+}
@@ -0,0 +1,232 @@
+use chess::Board;
+use chess::ChessMove;
+use shakmaty::Position;
+use std::io::BufRead;
+use std::str::FromStr;
+use crate::config::Config;
+use crate::convert;
+use crate::notation;
+
+/// Directory with the Syzygy tables for up to five pieces
+pub const DIRECTORY: &str = "3-4-5";
+
+/// Result with perfect play for one side. Cursed wins and blessed losses
+/// are draws because of the fifty move rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win,
+}
+
+impl Wdl {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Wdl::Loss => "Loss",
+            Wdl::BlessedLoss => "Blessed loss",
+            Wdl::Draw => "Draw",
+            Wdl::CursedWin => "Cursed win",
+            Wdl::Win => "Win",
+        }
+    }
+
+    // The same result for the opponent
+    pub fn flip(self) -> Wdl {
+        match self {
+            Wdl::Loss => Wdl::Win,
+            Wdl::BlessedLoss => Wdl::CursedWin,
+            Wdl::Draw => Wdl::Draw,
+            Wdl::CursedWin => Wdl::BlessedLoss,
+            Wdl::Win => Wdl::Loss,
+        }
+    }
+}
+
+/// `dtz` counts the plies to the next capture or pawn move with perfect
+/// play and is negative if the side the result belongs to loses.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Probe {
+    pub wdl: Wdl,
+    pub dtz: i32,
+}
+
+pub struct Tablebase {
+    tables: shakmaty_syzygy::Tablebase<shakmaty::Chess>,
+}
+
+impl Tablebase {
+    pub fn load(directory: &str) -> Self {
+        let mut tables = shakmaty_syzygy::Tablebase::new();
+        if tables.add_directory(directory).is_err() {
+            eprintln!("Tablebase not loaded");
+        }
+        Tablebase { tables }
+    }
+
+    /// Perfect move and its eval from White's point of view, `None` if the
+    /// position has too many pieces, castling rights or no table. Like all
+    /// probes it takes the halfmove clock in plies.
+    pub fn best_move(&self, brett: &Board, halfmove_clock: u32) -> Option<(ChessMove, f32)> {
+        let pos = position(brett, halfmove_clock)?;
+
+        let reward = match brett.side_to_move() {
+            chess::Color::White =>  100000.0,
+            chess::Color::Black => -100000.0,
+        };
+
+        let eval = match self.tables.probe_wdl_after_zeroing(&pos) {
+            Ok(shakmaty_syzygy::Wdl::Loss) => -reward,
+            Ok(shakmaty_syzygy::Wdl::Win) => reward,
+            _ => 0.0,
+        };
+
+        match self.tables.best_move(&pos) {
+            Ok(Some((m,_))) => convert::from_shakmaty_move(&m).map(|m| (m, eval)),
+            _ => {
+                eprintln!("Tablebase not found");
+                None
+            },
+        }
+    }
+
+    /// Result for the side to move, `None` like `best_move`.
+    pub fn probe(&self, brett: &Board, halfmove_clock: u32) -> Option<Probe> {
+        self.probe_position(&position(brett, halfmove_clock)?)
+    }
+
+    /// Every legal move with its result for the side playing it, the best
+    /// first: wins by the fewest plies, losses by the most. Moves whose
+    /// table is missing are left out.
+    pub fn probe_moves(&self, brett: &Board, halfmove_clock: u32) -> Option<Vec<(ChessMove, Probe)>> {
+        let pos = position(brett, halfmove_clock)?;
+        let mut moves = Vec::new();
+        for m in pos.legal_moves() {
+            let Ok(after) = pos.clone().play(&m) else {
+                continue;
+            };
+            let (Some(probe), Some(chess_move)) = (self.probe_position(&after), convert::from_shakmaty_move(&m)) else {
+                continue;
+            };
+            let wdl = probe.wdl.flip();
+            // The move is one ply more, a capture, pawn move or mate counts as just itself
+            let dtz = match (m.is_zeroing() || after.is_checkmate(), wdl) {
+                (_, Wdl::Draw) => 0,
+                (true, Wdl::Win | Wdl::CursedWin) => 1,
+                (true, Wdl::Loss | Wdl::BlessedLoss) => -1,
+                (false, _) => shakmaty_syzygy::Dtz(-probe.dtz).add_plies(1).0,
+            };
+            moves.push((chess_move, Probe { wdl, dtz }));
+        }
+        moves.sort_by_key(|(_, probe)| (std::cmp::Reverse(probe.wdl), probe.dtz));
+        Some(moves)
+    }
+
+    fn probe_position(&self, pos: &shakmaty::Chess) -> Option<Probe> {
+        let wdl = match self.tables.probe_wdl(pos).ok()?.after_zeroing() {
+            shakmaty_syzygy::Wdl::Loss => Wdl::Loss,
+            shakmaty_syzygy::Wdl::BlessedLoss => Wdl::BlessedLoss,
+            shakmaty_syzygy::Wdl::Draw => Wdl::Draw,
+            shakmaty_syzygy::Wdl::CursedWin => Wdl::CursedWin,
+            shakmaty_syzygy::Wdl::Win => Wdl::Win,
+        };
+        let dtz = self.tables.probe_dtz(pos).ok()?.ignore_rounding().0;
+        Some(Probe { wdl, dtz })
+    }
+}
+
+// Only positions with three to five pieces and no castling rights are in the tables
+fn position(brett: &Board, halfmove_clock: u32) -> Option<shakmaty::Chess> {
+    if brett.castle_rights(chess::Color::White) != chess::CastleRights::NoRights || brett.castle_rights(chess::Color::Black) != chess::CastleRights::NoRights {
+        return None;
+    }
+    if brett.combined().popcnt() > 5 || brett.combined().popcnt() < 3 {
+        return None;
+    }
+    convert::to_shakmaty(brett, halfmove_clock)
+}
+
+/// `tablebase [fen...]`: WDL and DTZ of every position and the result of
+/// each of its legal moves. Without arguments the FENs are read from stdin,
+/// one per line.
+pub fn run(args: &[String], config: &Config) -> Result<(), String> {
+    if config.tablebase.path.is_empty() {
+        return Err("No tablebase configured, set tablebase.path".to_string());
+    }
+    let tablebase = Tablebase::load(&config.tablebase.path);
+    let fens: Vec<String> = match args.is_empty() {
+        true => std::io::stdin().lock().lines().collect::<Result<Vec<String>, _>>().map_err(|e| e.to_string())?,
+        false => args.to_vec(),
+    };
+    for fen in fens.iter().map(|fen| fen.trim()).filter(|fen| !fen.is_empty()) {
+        let brett = Board::from_str(fen).map_err(|e| format!("Invalid position {}: {}", fen, e))?;
+        let halfmove_clock = fen.split_whitespace().nth(4).and_then(|n| n.parse().ok()).unwrap_or(0);
+        println!("{}", fen);
+        let (Some(probe), Some(moves)) = (tablebase.probe(&brett, halfmove_clock), tablebase.probe_moves(&brett, halfmove_clock)) else {
+            println!("  Not in the tablebase\n");
+            continue;
+        };
+        println!("  WDL {}  DTZ {}", probe.wdl.name(), probe.dtz);
+        for (chess_move, probe) in moves {
+            println!("  {:<8} {:<12} DTZ {}", notation::san(chess_move, &brett), probe.wdl.name(), probe.dtz);
+        }
+        println!();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The tables are kept in git LFS, without them only positions outside the tables are tested
+    fn tables() -> Option<Tablebase> {
+        let tables = Tablebase::load(DIRECTORY);
+        let brett = Board::from_str("4k3/8/4K3/8/8/8/8/1Q6 w - - 0 1").unwrap();
+        tables.probe(&brett, 0).is_some().then_some(tables)
+    }
+
+    fn config(path: &str) -> Config {
+        let mut config = Config::default();
+        config.tablebase.path = path.to_string();
+        config
+    }
+
+    #[test]
+    fn positions_outside_the_tables() {
+        let tables = Tablebase::load(DIRECTORY);
+        assert_eq!(tables.probe(&Board::default(), 0), None);
+        let castling = Board::from_str("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        assert_eq!(tables.probe_moves(&castling, 0), None);
+        assert!(run(&["4k3/8/8/8/8/8/8/R3K3 w - - 0 1".to_string()], &config("")).is_err());
+        assert!(run(&["not a position".to_string()], &config(DIRECTORY)).is_err());
+    }
+
+    #[test]
+    fn probes() {
+        let Some(tables) = tables() else {
+            eprintln!("No usable tables in {}, skipped", DIRECTORY);
+            return;
+        };
+        // Qb8 is mate, which counts as one ply like a capture
+        let brett = Board::from_str("4k3/8/4K3/8/8/8/8/1Q6 w - - 0 1").unwrap();
+        assert_eq!(tables.probe(&brett, 0), Some(Probe { wdl: Wdl::Win, dtz: 1 }));
+        let moves = tables.probe_moves(&brett, 0).unwrap();
+        assert_eq!(moves[0], (ChessMove::new(chess::Square::B1, chess::Square::B8, None), Probe { wdl: Wdl::Win, dtz: 1 }));
+        assert!(moves.iter().all(|(_, probe)| probe.wdl == Wdl::Win || probe.wdl == Wdl::Draw));
+
+        // The best move needs as many plies as the position, one less is left after it
+        let brett = Board::from_str("8/8/8/3k4/8/8/8/KR6 w - - 0 1").unwrap();
+        let probe = tables.probe(&brett, 0).unwrap();
+        let (best, best_probe) = tables.probe_moves(&brett, 0).unwrap()[0];
+        assert_eq!((probe.wdl, best_probe), (Wdl::Win, probe));
+        let after = tables.probe(&brett.make_move_new(best), 1).unwrap();
+        assert_eq!(after, Probe { wdl: Wdl::Loss, dtz: -(probe.dtz - 1) });
+
+        // The halfmove clock is in plies, too close to the fifty moves the win is cursed
+        assert_eq!(tables.probe(&brett, (101 - probe.dtz) as u32).unwrap().wdl, Wdl::CursedWin);
+
+        assert!(run(&["8/8/8/3k4/8/8/8/KR6 w - - 0 1".to_string()], &config(DIRECTORY)).is_ok());
+    }
+}
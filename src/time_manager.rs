@@ -0,0 +1,162 @@
+use chess::ChessMove;
+use std::time::Duration;
+use std::time::Instant;
+
+// Kept back from every budget for move transfer and thread wake-ups
+const OVERHEAD: Duration = Duration::from_millis(30);
+// Given even when the clock is almost down, half the remaining time at most
+const MIN_BUDGET: Duration = Duration::from_millis(10);
+
+/// Decides how long a search may run. The soft limit is checked between
+/// iterations and adapted to the stability of the best move, the hard limit
+/// aborts a running iteration.
+pub struct TimeManager {
+    start: Instant,
+    soft: Duration,
+    hard: Duration,
+    adaptive: bool,
+    infinite: bool,
+    scale: f64,
+    last_best: Option<ChessMove>,
+    last_score: f32,
+}
+
+impl TimeManager {
+    pub fn new(remaining: Duration, increment: Duration, moves_to_go: Option<u32>, brett: &chess::Board) -> Self {
+        let moves_to_go = moves_to_go.unwrap_or_else(|| TimeManager::estimate_moves_to_go(brett)).max(1);
+        let usable = remaining.saturating_sub(OVERHEAD);
+        let soft = (usable / moves_to_go + increment * 3 / 4).min(usable / 2).max(MIN_BUDGET.min(remaining / 2));
+        let hard = (soft * 4).min(usable / 3).max(soft);
+        TimeManager {
+            start: Instant::now(),
+            soft,
+            hard,
+            adaptive: true,
+            infinite: false,
+            scale: 1.0,
+            last_best: None,
+            last_score: 0.0,
+        }
+    }
+
+    // A fixed time per move, like UCI `go movetime`
+    pub fn fixed(time: Duration) -> Self {
+        TimeManager {
+            start: Instant::now(),
+            soft: time,
+            hard: time,
+            adaptive: false,
+            infinite: false,
+            scale: 1.0,
+            last_best: None,
+            last_score: 0.0,
+        }
+    }
+
+    // Runs until stopped from outside, like UCI `go infinite`
+    pub fn infinite() -> Self {
+        TimeManager {
+            infinite: true,
+            ..TimeManager::fixed(Duration::ZERO)
+        }
+    }
+
+    // Fewer moves are left to play the less material is on the board
+    fn estimate_moves_to_go(brett: &chess::Board) -> u32 {
+        let phase = (brett.pieces(chess::Piece::Knight) | brett.pieces(chess::Piece::Bishop)).popcnt()
+            + brett.pieces(chess::Piece::Rook).popcnt() * 2
+            + brett.pieces(chess::Piece::Queen).popcnt() * 4;
+        (15 + phase * 25 / 24).min(40)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    pub fn hard_deadline(&self) -> Option<Instant> {
+        match self.infinite {
+            true => None,
+            false => Some(self.start + self.hard),
+        }
+    }
+
+    /// Feeds the result of a finished iteration, scores are from the point
+    /// of view of the side to move.
+    pub fn update(&mut self, best: ChessMove, score: f32) {
+        if !self.adaptive {
+            return;
+        }
+        match self.last_best {
+            Some(last) if last == best => self.scale = (self.scale * 0.85).max(0.4),
+            Some(_) => self.scale = self.scale.max(1.0) * 1.5,
+            None => (),
+        }
+        if self.last_best.is_some() && score < self.last_score - 0.3 {
+            self.scale *= 1.3;
+        }
+        self.scale = self.scale.min(3.0);
+        self.last_best = Some(best);
+        self.last_score = score;
+    }
+
+    pub fn should_stop(&self) -> bool {
+        if self.infinite {
+            return false;
+        }
+        if !self.adaptive {
+            return self.elapsed() >= self.soft;
+        }
+        let limit = self.soft.mul_f64(self.scale).min(self.hard);
+        // The next iteration takes several times as long as this one, don't start it if it can't finish
+        self.elapsed() > limit.mul_f64(0.6)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::thread;
+
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn allocation() {
+        let brett = chess::Board::default();
+        let tm = TimeManager::new(ms(60_000), Duration::ZERO, Some(30), &brett);
+        assert_eq!((tm.soft, tm.hard), (ms(1999), ms(7996)));
+        // Most of the increment is used, the hard limit stays below a third of the clock
+        let tm = TimeManager::new(ms(9030), ms(2000), Some(40), &brett);
+        assert_eq!((tm.soft, tm.hard), (ms(1725), ms(3000)));
+        // With the clock almost down there is still time for a first search
+        let tm = TimeManager::new(ms(20), Duration::ZERO, None, &brett);
+        assert_eq!((tm.soft, tm.hard), (ms(10), ms(10)));
+        assert_eq!(TimeManager::estimate_moves_to_go(&brett), 40);
+        assert_eq!(TimeManager::estimate_moves_to_go(&chess::Board::from_str("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap()), 17);
+    }
+
+    #[test]
+    fn should_stop() {
+        let brett = chess::Board::default();
+        let fixed = TimeManager::fixed(ms(300));
+        let infinite = TimeManager::infinite();
+        let mut stable = TimeManager::new(ms(30_000), Duration::ZERO, Some(30), &brett);
+        let mut unstable = TimeManager::new(ms(30_000), Duration::ZERO, Some(30), &brett);
+        let (e4, d4) = (ChessMove::new(chess::Square::E2, chess::Square::E4, None), ChessMove::new(chess::Square::D2, chess::Square::D4, None));
+        stable.update(e4, 0.2);
+        stable.update(e4, 0.2);
+        unstable.update(e4, 0.2);
+        unstable.update(d4, -0.3);
+        assert!(!fixed.should_stop() && !stable.should_stop() && !unstable.should_stop());
+        assert_eq!(infinite.hard_deadline(), None);
+
+        // A stable best move stops early, a changing one gets more time
+        thread::sleep(ms(700));
+        assert!(fixed.should_stop());
+        assert!(stable.should_stop());
+        assert!(!unstable.should_stop());
+        assert!(!infinite.should_stop());
+    }
+}
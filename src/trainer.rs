@@ -0,0 +1,230 @@
+//! Endgame training: random won positions of a chosen material, which the
+//! human has to convert against the perfect defence of the tablebase.
+
+use chess::Board;
+use chess::BoardBuilder;
+use chess::BoardStatus;
+use chess::Color;
+use chess::Piece;
+use chess::Square;
+use rand::seq::SliceRandom;
+use crate::chess960;
+use crate::game_record::GameRecord;
+use crate::tablebase::Tablebase;
+use crate::tablebase::Wdl;
+
+pub const PRESETS: [&str; 7] = ["KQvK", "KRvK", "KBBvK", "KBNvK", "KPvK", "KQvKR", "KRvKP"];
+
+// Random positions tried before giving up, most are no win or not even legal
+const ATTEMPTS: usize = 10000;
+
+/// Pieces of both sides like `KBNvK`, the winning side first. It plays White.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Material {
+    name: String,
+    white: Vec<Piece>,
+    black: Vec<Piece>,
+}
+
+impl Material {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let (white, black) = text.split_once('v').ok_or(format!("{}: expected two sides like KBNvK", text))?;
+        let pieces = |side: &str| side.chars().map(|c| match c.to_ascii_uppercase() {
+            'K' => Ok(Piece::King),
+            'Q' => Ok(Piece::Queen),
+            'R' => Ok(Piece::Rook),
+            'B' => Ok(Piece::Bishop),
+            'N' => Ok(Piece::Knight),
+            'P' => Ok(Piece::Pawn),
+            c => Err(format!("{}: unknown piece {}", text, c)),
+        }).collect::<Result<Vec<Piece>, String>>();
+        let (white, black) = (pieces(white)?, pieces(black)?);
+        for side in [&white, &black] {
+            if side.iter().filter(|p| **p == Piece::King).count() != 1 || side.first() != Some(&Piece::King) {
+                return Err(format!("{}: every side needs exactly one king, written first", text));
+            }
+        }
+        if white.len() + black.len() > 5 {
+            return Err(format!("{}: the tablebase only has up to five pieces", text));
+        }
+        Ok(Material { name: text.to_uppercase().replace('V', "v"), white, black })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Random position with White to move that the tablebase rates as a win.
+    pub fn random_position(&self, tables: &Tablebase) -> Option<Board> {
+        (0..ATTEMPTS)
+            .filter_map(|_| self.random_board())
+            .find(|brett| tables.probe(brett, 0).is_some_and(|probe| probe.wdl == Wdl::Win))
+    }
+
+    // Legal placement of the pieces, `None` if the random squares don't work out
+    fn random_board(&self) -> Option<Board> {
+        let mut rng = rand::thread_rng();
+        let mut squares = chess::ALL_SQUARES.to_vec();
+        squares.shuffle(&mut rng);
+        let mut builder = BoardBuilder::new();
+        let mut kings = Vec::new();
+        let pieces = self.white.iter().map(|p| (*p, Color::White)).chain(self.black.iter().map(|p| (*p, Color::Black)));
+        for ((piece, color), square) in pieces.zip(squares) {
+            if piece == Piece::Pawn && matches!(square.get_rank(), chess::Rank::First | chess::Rank::Eighth) {
+                return None;
+            }
+            if piece == Piece::King {
+                kings.push(square);
+            }
+            builder.piece(square, piece, color);
+        }
+        if distance(kings[0], kings[1]) < 2 {
+            return None;
+        }
+        builder.side_to_move(Color::White);
+        Board::try_from(&builder).ok()
+    }
+}
+
+fn distance(a: Square, b: Square) -> usize {
+    let files = a.get_file().to_index().abs_diff(b.get_file().to_index());
+    let ranks = a.get_rank().to_index().abs_diff(b.get_rank().to_index());
+    files.max(ranks)
+}
+
+/// How the conversion went so far, see `Training::progress`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Progress {
+    pub plies: usize,
+    // Ply after which White no longer wins and what the result became
+    pub lost: Option<(usize, Wdl)>,
+    // Ply of the first capture, pawn move or mate, which is what DTZ counts
+    pub converted: Option<usize>,
+}
+
+pub struct Training {
+    material: Material,
+    start: Board,
+    start_dtz: i32,
+}
+
+impl Training {
+    pub fn new(material: Material, tables: &Tablebase) -> Result<Self, String> {
+        let start = material.random_position(tables).ok_or(format!("No won {} position found, is the tablebase there?", material.name()))?;
+        let start_dtz = tables.probe(&start, 0).map_or(0, |probe| probe.dtz);
+        Ok(Training { material, start, start_dtz })
+    }
+
+    pub fn material(&self) -> &Material {
+        &self.material
+    }
+
+    pub fn start(&self) -> Board {
+        self.start
+    }
+
+    // Plies to the first capture, pawn move or mate with perfect play on both sides
+    pub fn start_dtz(&self) -> i32 {
+        self.start_dtz
+    }
+
+    /// Follows the game from the start position up to the current ply.
+    pub fn progress(&self, tables: &Tablebase, record: &GameRecord) -> Progress {
+        let positions: Vec<Board> = record.positions().map(|(brett, _)| brett).collect();
+        let mut progress = Progress { plies: positions.len() - 1, lost: None, converted: None };
+        let mut halfmove_clock = 0;
+        for (ply, (before, chess_move)) in positions.iter().zip(record.moves()).enumerate() {
+            let after = positions[ply + 1];
+            halfmove_clock = match chess960::is_zeroing(before, chess_move) {
+                true => 0,
+                false => halfmove_clock + 1,
+            };
+            if progress.converted.is_none() && (halfmove_clock == 0 || after.status() == BoardStatus::Checkmate) {
+                progress.converted = Some(ply + 1);
+            }
+            if progress.lost.is_none() {
+                if let Some(probe) = tables.probe(&after, halfmove_clock) {
+                    let wdl = match after.side_to_move() {
+                        Color::White => probe.wdl,
+                        Color::Black => probe.wdl.flip(),
+                    };
+                    if wdl != Wdl::Win {
+                        progress.lost = Some((ply + 1, wdl));
+                    }
+                }
+            }
+        }
+        progress
+    }
+
+    pub fn report(&self, progress: &Progress) -> String {
+        match (progress.lost, progress.converted) {
+            (Some((ply, wdl)), _) => format!("{}: win lost at ply {}, {}", self.material.name(), ply, wdl.name()),
+            (None, Some(ply)) => format!("{}: converted in {} plies, DTZ {}", self.material.name(), ply, self.start_dtz),
+            (None, None) => format!("{}: ply {}, DTZ {}", self.material.name(), progress.plies, self.start_dtz),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn material_and_random_boards() {
+        let material = Material::parse("kbnvk").unwrap();
+        assert_eq!(material.name(), "KBNvK");
+        assert!(Material::parse("KBN").is_err());
+        assert!(Material::parse("BKvK").is_err());
+        assert!(Material::parse("KQQvKRR").is_err());
+
+        let material = Material::parse("KRvKP").unwrap();
+        let boards: Vec<Board> = (0..200).filter_map(|_| material.random_board()).collect();
+        assert!(!boards.is_empty());
+        for brett in boards {
+            assert_eq!(brett.side_to_move(), Color::White);
+            assert_eq!(brett.pieces(Piece::Rook) & brett.color_combined(Color::White), *brett.pieces(Piece::Rook));
+            assert_eq!((brett.pieces(Piece::Pawn) & brett.color_combined(Color::Black)).popcnt(), 1);
+            assert!(distance(brett.king_square(Color::White), brett.king_square(Color::Black)) >= 2);
+        }
+    }
+
+    fn conversion(material: &str, fen: &str, moves: &str, tables: &Tablebase) -> (Training, GameRecord) {
+        let start = Board::from_str(fen).unwrap();
+        // Without usable tables the DTZ is made up, the tables are kept in git LFS
+        let start_dtz = tables.probe(&start, 0).map_or(3, |probe| probe.dtz);
+        let training = Training { material: Material::parse(material).unwrap(), start, start_dtz };
+        let mut record = GameRecord::new(start, chess960::Castling::none(), 0);
+        for m in moves.split_whitespace() {
+            record.play(crate::convert::from_uci(&record.board(), m).unwrap());
+        }
+        (training, record)
+    }
+
+    #[test]
+    fn progress_and_report() {
+        let tables = Tablebase::load(crate::tablebase::DIRECTORY);
+        let usable = tables.probe(&Board::from_str("7k/8/6K1/8/8/1Q6/8/8 w - - 0 1").unwrap(), 0).is_some();
+
+        // Qb8 would mate at once, the long way still converts
+        let (training, record) = conversion("KQvK", "7k/8/6K1/8/8/1Q6/8/8 w - - 0 1", "b3b7 h8g8 b7g7", &tables);
+        let progress = training.progress(&tables, &record);
+        assert_eq!((progress.plies, progress.converted, progress.lost), (3, Some(3), None));
+        assert_eq!(training.report(&progress), format!("KQvK: converted in 3 plies, DTZ {}", training.start_dtz()));
+
+        let (training, record) = conversion("KRvK", "4k3/8/4K3/8/8/8/8/R7 w - - 0 1", "e6d6 e8f8", &tables);
+        let progress = training.progress(&tables, &record);
+        assert_eq!((progress.plies, progress.converted, progress.lost), (2, None, None));
+        assert_eq!(training.report(&progress), format!("KRvK: ply 2, DTZ {}", training.start_dtz()));
+
+        // Stalemate throws the win away
+        let (training, record) = conversion("KQvK", "7k/8/6K1/8/8/1Q6/8/8 w - - 0 1", "b3f7", &tables);
+        let progress = training.progress(&tables, &record);
+        assert_eq!(progress.converted, None);
+        if usable {
+            assert_eq!(progress.lost, Some((1, Wdl::Draw)));
+            assert_eq!(training.report(&progress), "KQvK: win lost at ply 1, Draw");
+        }
+    }
+}
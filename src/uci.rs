@@ -0,0 +1,455 @@
+use chess::Board;
+use std::io::BufRead;
+use std::io::Write;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+use crate::chess960;
+use crate::chess960::Castling;
+use crate::config::Config;
+use crate::convert;
+use crate::engine;
+use crate::skill;
+use crate::skill::Skill;
+use crate::time_manager::TimeManager;
+
+const MAX_MULTI_PV: usize = 5;
+
+// Written to by the command loop and by the thread that prints the search
+type Output = Arc<Mutex<dyn Write + Send>>;
+
+struct Search {
+    stop: Arc<AtomicBool>,
+    output: thread::JoinHandle<()>,
+}
+
+/// Speaks the UCI protocol on stdin/stdout until `quit` or the end of input.
+/// With UCI_Chess960 castling is only considered as the move to play, not
+/// deeper in the search.
+pub fn run(config: Config) -> Result<(), String> {
+    session(config, std::io::stdin().lock().lines(), Arc::new(Mutex::new(std::io::stdout())))
+}
+
+fn session(mut config: Config, lines: impl Iterator<Item = std::io::Result<String>>, out: Output) -> Result<(), String> {
+    let mut pool = Arc::new(config.thread_pool()?);
+    let mut brett = Board::default();
+    let mut castling = Castling::none();
+    let mut fifty_move_counter = 0;
+    let mut uci_chess960 = false;
+    let mut multi_pv = 1;
+    let mut skill_level = skill::MAX_LEVEL;
+    let mut limit_strength = false;
+    let mut elo = 1600;
+    let mut show_stats = false;
+    let mut search: Option<Search> = None;
+
+    for line in lines {
+        let line = line.map_err(|e| e.to_string())?;
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first().copied() {
+            Some("uci") => {
+                say(&out, "id name Schach2");
+                say(&out, "id author Tomyyy-1337");
+                say(&out, &format!("option name MultiPV type spin default 1 min 1 max {}", MAX_MULTI_PV));
+                say(&out, &format!("option name Skill Level type spin default {} min 0 max {}", skill::MAX_LEVEL, skill::MAX_LEVEL));
+                say(&out, "option name UCI_LimitStrength type check default false");
+                // Chess960 castling is only searched at the root, deeper in the tree
+                // neither side castles, the search misjudges lines that depend on it
+                say(&out, "option name UCI_Chess960 type check default false");
+                say(&out, "option name SearchStats type check default false");
+                say(&out, &format!("option name UCI_Elo type spin default 1600 min {} max {}", skill::MIN_ELO, skill::MAX_ELO));
+                say(&out, &format!("option name Threads type spin default {} min 1 max 256", config.engine.threads));
+                say(&out, &format!("option name MoveTime type spin default {} min 1 max 3600000", config.engine.move_time));
+                say(&out, &format!("option name SyzygyPath type string default {}", if config.tablebase.path.is_empty() { "<empty>" } else { &config.tablebase.path }));
+                say(&out, "uciok");
+            },
+            Some("isready") => say(&out, "readyok"),
+            Some("setoption") => {
+                if let Some((name, value)) = parse_option(&tokens) {
+                    match name.as_str() {
+                        "multipv" => match value.parse::<usize>() {
+                            Ok(n) => multi_pv = n.clamp(1, MAX_MULTI_PV),
+                            Err(_) => eprintln!("Invalid MultiPV value: {}", value),
+                        },
+                        "skill level" => match value.parse::<u8>() {
+                            Ok(level) => skill_level = level.min(skill::MAX_LEVEL),
+                            Err(_) => eprintln!("Invalid Skill Level value: {}", value),
+                        },
+                        "uci_limitstrength" => limit_strength = value == "true",
+                        "uci_chess960" => uci_chess960 = value == "true",
+                        "searchstats" => show_stats = value == "true",
+                        "uci_elo" => match value.parse::<u32>() {
+                            Ok(n) => elo = n.clamp(skill::MIN_ELO, skill::MAX_ELO),
+                            Err(_) => eprintln!("Invalid UCI_Elo value: {}", value),
+                        },
+                        "threads" | "movetime" | "syzygypath" => {
+                            let mut changed = config.clone();
+                            let valid = match name.as_str() {
+                                "threads" => value.parse().map(|n| changed.engine.threads = n).is_ok(),
+                                "movetime" => value.parse().map(|n| changed.engine.move_time = n).is_ok(),
+                                _ => {
+                                    changed.tablebase.path = if value == "<empty>" { String::new() } else { value.clone() };
+                                    true
+                                },
+                            };
+                            match (valid, changed.validate()) {
+                                (false, _) => eprintln!("Invalid {} value: {}", name, value),
+                                (true, Err(e)) => eprintln!("{}", e),
+                                (true, Ok(())) => {
+                                    if changed.engine.threads != config.engine.threads {
+                                        pool = Arc::new(changed.thread_pool()?);
+                                    }
+                                    config = changed;
+                                },
+                            }
+                        },
+                        _ => eprintln!("Unknown option: {}", name),
+                    }
+                }
+            },
+            Some("ucinewgame") => {
+                finish(&mut search);
+                brett = Board::default();
+                castling = Castling::none();
+                fifty_move_counter = 0;
+            },
+            Some("position") => {
+                finish(&mut search);
+                match parse_position(&tokens, uci_chess960) {
+                    Ok((position, rights, fifty)) => (brett, castling, fifty_move_counter) = (position, rights, fifty),
+                    Err(e) => eprintln!("{}", e),
+                }
+            },
+            Some("go") => {
+                finish(&mut search);
+                let (time_manager, depth_limit) = parse_go(&tokens, &brett, config.engine.move_time());
+                let skill = match limit_strength {
+                    true => Skill::from_elo(elo),
+                    false => Skill::new(skill_level),
+                };
+                let mut engine = engine::Engine::new(brett);
+                engine.set_multi_pv(multi_pv);
+                engine.set_skill(skill);
+                engine.set_tablebase(&config.tablebase.path);
+                engine.set_castling(castling);
+                if let Some(depth) = depth_limit {
+                    engine.set_depth_limit(depth);
+                }
+                let infinite = tokens.contains(&"infinite");
+                search = Some(start(engine, brett, fifty_move_counter, time_manager, infinite, show_stats, &config, pool.clone(), out.clone()));
+            },
+            Some("stop") => finish(&mut search),
+            Some("quit") => break,
+            Some(command) => eprintln!("Unknown command: {}", command),
+            None => (),
+        }
+    }
+    finish(&mut search);
+    Ok(())
+}
+
+fn say(out: &Output, line: &str) {
+    let mut out = out.lock().unwrap();
+    let _ = writeln!(out, "{}", line);
+    let _ = out.flush();
+}
+
+// With `infinite` the `bestmove` waits for `stop` or `quit`, even if the search ends before
+#[allow(clippy::too_many_arguments)]
+fn start(mut engine: engine::Engine, brett: Board, fifty_move_counter: u8, time_manager: TimeManager, infinite: bool, show_stats: bool, config: &Config, pool: Arc<rayon::ThreadPool>, out: Output) -> Search {
+    let (tx, rx) = mpsc::channel();
+    engine.send_updates(tx.clone());
+    let stop = engine.stop_flag();
+    let stopped = stop.clone();
+    let (depth, max_depth) = (config.engine.depth, config.engine.max_depth);
+    thread::spawn(move || {
+        let update = match pool.install(|| engine.best_move(fifty_move_counter, depth, max_depth, time_manager)) {
+            Some((chess_move, fifty, info)) => engine::SearchUpdate::BestMove(chess_move, fifty, info),
+            None => engine::SearchUpdate::NoMove,
+        };
+        let _ = tx.send(update);
+    });
+    // Printing happens on its own thread so `stop` can be read while searching
+    let output = thread::spawn(move || {
+        for update in rx {
+            let best_move = match update {
+                engine::SearchUpdate::Info(info) => {
+                    print_info(&out, &brett, &info);
+                    if show_stats {
+                        print_stats(&out, &info);
+                    }
+                    continue;
+                },
+                engine::SearchUpdate::BestMove(chess_move, _, info) => {
+                    if show_stats {
+                        print_stats(&out, &info);
+                    }
+                    chess_move.to_string()
+                },
+                // The null move of UCI
+                engine::SearchUpdate::NoMove => "0000".to_string(),
+            };
+            while infinite && !stopped.load(Ordering::Relaxed) {
+                thread::park();
+            }
+            say(&out, &format!("bestmove {}", best_move));
+            break;
+        }
+    });
+    Search { stop, output }
+}
+
+// Stops a running search and waits until its `bestmove` is printed
+fn finish(search: &mut Option<Search>) {
+    if let Some(search) = search.take() {
+        search.stop.store(true, Ordering::Relaxed);
+        search.output.thread().unpark();
+        let _ = search.output.join();
+    }
+}
+
+fn print_info(out: &Output, brett: &Board, info: &engine::SearchInfo) {
+    let factor = match brett.side_to_move() {
+        chess::Color::White => 1.0,
+        chess::Color::Black => -1.0,
+    };
+    let nps = (info.stats.nodes as f64 / info.time.as_secs_f64().max(0.001)) as u64;
+    for (i, line) in info.lines.iter().enumerate() {
+        let pv: Vec<String> = line.pv.iter().map(|m| m.to_string()).collect();
+        say(out, &format!(
+            "info depth {} seldepth {} multipv {} score {} nodes {} nps {} time {} pv {}",
+            info.depth, info.stats.seldepth, i + 1, score(factor * line.eval, info.tablebase),
+            info.stats.nodes, nps, info.time.as_millis(), pv.join(" "),
+        ));
+    }
+}
+
+fn print_stats(out: &Output, info: &engine::SearchInfo) {
+    match info.branching_factor {
+        Some(branching_factor) => say(out, &format!("info string {} ebf {:.2}", info.stats, branching_factor)),
+        None => say(out, &format!("info string {}", info.stats)),
+    }
+}
+
+// `eval` is in pawns from the point of view of the side to move
+fn score(eval: f32, tablebase: bool) -> String {
+    if tablebase {
+        return match eval {
+            e if e > 0.0 => "cp 20000".to_string(),
+            e if e < 0.0 => "cp -20000".to_string(),
+            _ => "cp 0".to_string(),
+        };
+    }
+    if eval.abs() >= 1000.0 {
+        // Mates are scored 1000 + 4000 / (plies + 1)
+        let plies = (4000.0 / (eval.abs() - 1000.0)).round() as i32 - 1;
+        return format!("mate {}", eval.signum() as i32 * (plies + 1) / 2);
+    }
+    format!("cp {}", (eval * 100.0).round() as i32)
+}
+
+fn parse_option(tokens: &[&str]) -> Option<(String, String)> {
+    let name = tokens.iter().position(|t| *t == "name")?;
+    let value = tokens.iter().position(|t| *t == "value").unwrap_or(tokens.len());
+    let name = tokens.get(name + 1..value)?.join(" ").to_lowercase();
+    let value = tokens.get(value + 1..).unwrap_or_default().join(" ");
+    Some((name, value))
+}
+
+// The halfmove clock is passed on in plies, from 100 on the engine scores a
+// draw either way. With UCI_Chess960 the castling rights are kept apart from
+// the board and castling is sent as the king taking its own rook.
+fn parse_position(tokens: &[&str], uci_chess960: bool) -> Result<(Board, Castling, u8), String> {
+    let moves = tokens.iter().position(|t| *t == "moves").unwrap_or(tokens.len());
+    let (mut brett, mut castling, mut halfmoves) = match (tokens.get(1).copied(), uci_chess960) {
+        (Some("startpos"), false) => (Board::default(), Castling::none(), 0),
+        (Some("startpos"), true) => {
+            let (brett, castling) = chess960::start_position(chess960::STANDARD).unwrap();
+            (brett, castling, 0)
+        },
+        (Some("fen"), false) => {
+            let fen = tokens[2..moves].join(" ");
+            let brett = Board::from_str(&fen).map_err(|e| format!("Invalid FEN {}: {}", fen, e))?;
+            let halfmoves = tokens.get(6).and_then(|t| t.parse::<u32>().ok()).unwrap_or(0);
+            (brett, Castling::none(), halfmoves)
+        },
+        (Some("fen"), true) => {
+            let (brett, castling, halfmoves, _) = chess960::from_fen(&tokens[2..moves].join(" "))?;
+            (brett, castling, halfmoves)
+        },
+        _ => return Err("Expected startpos or fen".to_string()),
+    };
+    for token in tokens.iter().skip(moves + 1) {
+        let chess_move = match uci_chess960 {
+            true => chess960::from_uci(&brett, castling, token),
+            false => convert::from_uci(&brett, token),
+        }.ok_or(format!("Illegal move: {}", token))?;
+        if chess960::is_zeroing(&brett, chess_move) {
+            halfmoves = 0;
+        } else {
+            halfmoves += 1;
+        }
+        (brett, castling) = chess960::play(&brett, castling, chess_move);
+    }
+    Ok((brett, castling, halfmoves.min(100) as u8))
+}
+
+// `go depth` without a time limit searches until the depth is done
+fn parse_go(tokens: &[&str], brett: &Board, move_time: Duration) -> (TimeManager, Option<u64>) {
+    let value = |name: &str| tokens.iter()
+        .position(|t| *t == name)
+        .and_then(|i| tokens.get(i + 1))
+        .and_then(|t| t.parse::<u64>().ok());
+    let depth = value("depth").map(|depth| depth.max(1));
+    if tokens.contains(&"infinite") {
+        return (TimeManager::infinite(), depth);
+    }
+    if let Some(movetime) = value("movetime") {
+        return (TimeManager::fixed(Duration::from_millis(movetime)), depth);
+    }
+    let (time, increment) = match brett.side_to_move() {
+        chess::Color::White => (value("wtime"), value("winc")),
+        chess::Color::Black => (value("btime"), value("binc")),
+    };
+    let time_manager = match (time, depth) {
+        (Some(time), _) => TimeManager::new(
+            Duration::from_millis(time),
+            Duration::from_millis(increment.unwrap_or(0)),
+            value("movestogo").map(|n| n as u32),
+            brett,
+        ),
+        (None, Some(_)) => TimeManager::infinite(),
+        // Same thinking time as the GUI without a clock
+        (None, None) => TimeManager::fixed(move_time),
+    };
+    (time_manager, depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    // Feeds commands to a session on its own thread and collects what it prints
+    struct Gui {
+        input: mpsc::Sender<std::io::Result<String>>,
+        printed: Arc<Mutex<Vec<u8>>>,
+        session: thread::JoinHandle<Result<(), String>>,
+    }
+
+    impl Gui {
+        fn new() -> Self {
+            let mut config = Config::default();
+            config.engine.threads = 2;
+            config.tablebase.path = String::new();
+            let (input, lines) = mpsc::channel();
+            let printed = Arc::new(Mutex::new(Vec::new()));
+            let out: Output = printed.clone();
+            let session = thread::spawn(move || session(config, lines.into_iter(), out));
+            Gui { input, printed, session }
+        }
+
+        fn send(&self, command: &str) {
+            self.input.send(Ok(command.to_string())).unwrap();
+        }
+
+        fn lines(&self) -> Vec<String> {
+            String::from_utf8(self.printed.lock().unwrap().clone()).unwrap().lines().map(|l| l.to_string()).collect()
+        }
+
+        // Waits up to ten seconds for a line starting with `prefix`
+        fn wait_for(&self, prefix: &str) -> String {
+            let start = Instant::now();
+            while start.elapsed() < Duration::from_secs(10) {
+                if let Some(line) = self.lines().into_iter().find(|l| l.starts_with(prefix)) {
+                    return line;
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
+            panic!("no {} in {:?}", prefix, self.lines());
+        }
+
+        fn quit(self) {
+            self.send("quit");
+            self.session.join().unwrap().unwrap();
+        }
+    }
+
+    #[test]
+    fn go_movetime() {
+        let gui = Gui::new();
+        gui.send("position startpos moves e2e4");
+        let start = Instant::now();
+        gui.send("go movetime 300");
+        let best_move = gui.wait_for("bestmove");
+        assert!(start.elapsed() < Duration::from_secs(3));
+        let brett = Board::default().make_move_new(convert::from_uci(&Board::default(), "e2e4").unwrap());
+        assert!(convert::from_uci(&brett, best_move.split_whitespace().nth(1).unwrap()).is_some());
+        gui.quit();
+    }
+
+    #[test]
+    fn go_infinite_waits_for_stop() {
+        let gui = Gui::new();
+        // Mate in one, the search is done long before `stop`
+        gui.send("position fen 6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1");
+        gui.send("go infinite");
+        gui.wait_for("info depth");
+        thread::sleep(Duration::from_millis(300));
+        assert!(!gui.lines().iter().any(|l| l.starts_with("bestmove")));
+        gui.send("stop");
+        assert_eq!(gui.wait_for("bestmove"), "bestmove a1a8");
+        gui.quit();
+    }
+
+    #[test]
+    fn position_during_infinite_search() {
+        let gui = Gui::new();
+        gui.send("go infinite");
+        gui.send("position startpos moves d2d4");
+        gui.send("isready");
+        gui.wait_for("readyok");
+        assert_eq!(gui.lines().iter().filter(|l| l.starts_with("bestmove")).count(), 1);
+        gui.quit();
+    }
+
+    #[test]
+    fn multipv_and_go_depth() {
+        let gui = Gui::new();
+        gui.send("setoption name MultiPV value 3");
+        gui.send("position startpos");
+        gui.send("go depth 3");
+        gui.wait_for("bestmove");
+        let lines = gui.lines();
+        let last_depth: Vec<&String> = lines.iter().filter(|l| l.starts_with("info depth 3 ")).collect();
+        for multipv in 1..=3 {
+            assert!(last_depth.iter().any(|l| l.contains(&format!(" multipv {} ", multipv))), "{:?}", lines);
+        }
+        assert!(!lines.iter().any(|l| l.starts_with("info depth 4 ")));
+        gui.quit();
+    }
+
+    #[test]
+    fn bestmove_0000_when_mated() {
+        let gui = Gui::new();
+        gui.send("position fen R5k1/5ppp/8/8/8/8/5PPP/6K1 b - - 1 1");
+        gui.send("go movetime 100");
+        assert_eq!(gui.wait_for("bestmove"), "bestmove 0000");
+        gui.quit();
+    }
+
+    #[test]
+    fn halfmove_clock_in_plies() {
+        let tokens: Vec<&str> = "position fen 4k3/8/8/8/8/8/R7/4K3 w - - 97 80 moves a2a3 e8d8".split_whitespace().collect();
+        assert_eq!(parse_position(&tokens, false).unwrap().2, 99);
+        let tokens: Vec<&str> = "position fen 4k3/8/8/8/8/8/R7/4K3 w - - 0 80 moves a2a3 e8d8 a3a2".split_whitespace().collect();
+        assert_eq!(parse_position(&tokens, true).unwrap().2, 3);
+        let tokens: Vec<&str> = "position fen 4k3/8/8/8/8/8/P7/4K3 w - - 120 80 moves a2a3 e8d8".split_whitespace().collect();
+        assert_eq!(parse_position(&tokens, false).unwrap().2, 1);
+    }
+}
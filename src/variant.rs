@@ -0,0 +1,256 @@
+//! Chess variants played on shakmaty positions, since the chess crate only
+//! knows standard chess. Rules and notation come from shakmaty, the search
+//! is in `variant_engine`.
+
+use shakmaty::Color;
+use shakmaty::Move;
+use shakmaty::Position;
+use shakmaty::Role;
+use shakmaty::Square;
+use shakmaty::san::SanPlus;
+use shakmaty::variant::VariantPosition;
+use crate::game::Outcome;
+use crate::game::Player;
+use crate::game::Termination;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Variant {
+    KingOfTheHill,
+    ThreeCheck,
+    Atomic,
+    Crazyhouse,
+}
+
+// Pieces that can be in a Crazyhouse pocket
+pub const POCKET_ROLES: [Role; 5] = [Role::Pawn, Role::Knight, Role::Bishop, Role::Rook, Role::Queen];
+
+impl Variant {
+    pub const ALL: [Variant; 4] = [Variant::KingOfTheHill, Variant::ThreeCheck, Variant::Atomic, Variant::Crazyhouse];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Variant::KingOfTheHill => "King of the Hill",
+            Variant::ThreeCheck => "Three-check",
+            Variant::Atomic => "Atomic",
+            Variant::Crazyhouse => "Crazyhouse",
+        }
+    }
+
+    pub fn start(&self) -> VariantPosition {
+        VariantPosition::new(match self {
+            Variant::KingOfTheHill => shakmaty::variant::Variant::KingOfTheHill,
+            Variant::ThreeCheck => shakmaty::variant::Variant::ThreeCheck,
+            Variant::Atomic => shakmaty::variant::Variant::Atomic,
+            Variant::Crazyhouse => shakmaty::variant::Variant::Crazyhouse,
+        })
+    }
+
+    // Winning by the special rule of the variant
+    fn termination(&self) -> Termination {
+        match self {
+            Variant::KingOfTheHill => Termination::KingOfTheHill,
+            Variant::ThreeCheck => Termination::ThreeChecks,
+            Variant::Atomic => Termination::KingExploded,
+            Variant::Crazyhouse => Termination::Checkmate,
+        }
+    }
+}
+
+/// One game of a variant: the positions after every move, who plays which
+/// side and the result. Unlike `Game` there is no clock and no going forward
+/// again after taking back.
+pub struct VariantGame {
+    variant: Variant,
+    positions: Vec<VariantPosition>,
+    moves: Vec<Move>,
+    sans: Vec<String>,
+    players: [Player; 2],
+    result: Option<(Outcome, Termination)>,
+}
+
+impl VariantGame {
+    pub fn new(variant: Variant, players: [Player; 2]) -> Self {
+        VariantGame {
+            variant,
+            positions: vec![variant.start()],
+            moves: Vec::new(),
+            sans: Vec::new(),
+            players,
+            result: None,
+        }
+    }
+
+    pub fn variant(&self) -> Variant {
+        self.variant
+    }
+
+    pub fn position(&self) -> &VariantPosition {
+        self.positions.last().unwrap()
+    }
+
+    pub fn player_to_move(&self) -> Player {
+        match self.position().turn() {
+            Color::White => self.players[0],
+            Color::Black => self.players[1],
+        }
+    }
+
+    pub fn result(&self) -> Option<(Outcome, Termination)> {
+        self.result
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.result.is_some()
+    }
+
+    pub fn last_move(&self) -> Option<&Move> {
+        self.moves.last()
+    }
+
+    pub fn san_moves(&self) -> impl Iterator<Item = &str> + '_ {
+        self.sans.iter().map(|s| s.as_str())
+    }
+
+    /// Moves of the piece on `square`, none once the game is over.
+    pub fn legal_moves_from(&self, square: Square) -> Vec<Move> {
+        if self.is_over() {
+            return Vec::new();
+        }
+        self.position().legal_moves().into_iter().filter(|m| m.from() == Some(square)).collect()
+    }
+
+    /// Crazyhouse drops of `role` from the pocket of the side to move.
+    pub fn drops(&self, role: Role) -> Vec<Move> {
+        if self.is_over() {
+            return Vec::new();
+        }
+        self.position().legal_moves().into_iter().filter(|m| matches!(m, Move::Put { role: r, .. } if *r == role)).collect()
+    }
+
+    /// Pieces in the Crazyhouse pocket of `color`, empty in the other variants.
+    pub fn pocket(&self, color: Color) -> Vec<(Role, u8)> {
+        match self.position().pockets() {
+            Some(pockets) => POCKET_ROLES.iter().map(|role| (*role, *pockets.get(color).get(*role))).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // Checks `color` still has to give in Three-check
+    pub fn remaining_checks(&self, color: Color) -> Option<u32> {
+        self.position().remaining_checks().map(|checks| u32::from(*checks.get(color)))
+    }
+
+    pub fn play(&mut self, m: &Move) -> Result<(), String> {
+        if self.is_over() {
+            return Err("The game is over".to_string());
+        }
+        let pos = self.position().clone();
+        if !pos.is_legal(m) {
+            return Err(format!("Illegal move {}", m));
+        }
+        self.sans.push(SanPlus::from_move(pos.clone(), m).to_string());
+        self.moves.push(m.clone());
+        self.positions.push(pos.play(m).map_err(|e| e.to_string())?);
+        self.result = self.detect_result();
+        Ok(())
+    }
+
+    /// Undoes moves until a human is to move again, at least one.
+    pub fn take_back(&mut self) -> bool {
+        if self.moves.is_empty() {
+            return false;
+        }
+        self.undo();
+        while self.player_to_move() != Player::Human && !self.moves.is_empty() {
+            self.undo();
+        }
+        self.result = self.detect_result();
+        true
+    }
+
+    fn undo(&mut self) {
+        self.positions.pop();
+        self.moves.pop();
+        self.sans.pop();
+    }
+
+    fn detect_result(&self) -> Option<(Outcome, Termination)> {
+        let pos = self.position();
+        let outcome = match pos.outcome()? {
+            shakmaty::Outcome::Decisive { winner: Color::White } => Outcome::WhiteWins,
+            shakmaty::Outcome::Decisive { winner: Color::Black } => Outcome::BlackWins,
+            shakmaty::Outcome::Draw => Outcome::Draw,
+        };
+        let termination = if pos.is_variant_end() {
+            self.variant.termination()
+        } else if pos.is_checkmate() {
+            Termination::Checkmate
+        } else if pos.is_stalemate() {
+            Termination::Stalemate
+        } else {
+            Termination::InsufficientMaterial
+        };
+        Some((outcome, termination))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(game: &mut VariantGame, moves: &str) {
+        for san in moves.split_whitespace() {
+            let m = san.parse::<SanPlus>().unwrap().san.to_move(game.position()).unwrap();
+            game.play(&m).unwrap();
+        }
+    }
+
+    fn human_game(variant: Variant) -> VariantGame {
+        VariantGame::new(variant, [Player::Human, Player::Human])
+    }
+
+    #[test]
+    fn king_of_the_hill() {
+        let mut game = human_game(Variant::KingOfTheHill);
+        play(&mut game, "e3 e6 Ke2 Ke7 Kd3 Kd6");
+        assert_eq!(game.result(), None);
+        play(&mut game, "Ke4");
+        assert_eq!(game.result(), Some((Outcome::WhiteWins, Termination::KingOfTheHill)));
+        assert!(game.legal_moves_from(Square::A7).is_empty());
+    }
+
+    #[test]
+    fn three_check() {
+        let mut game = human_game(Variant::ThreeCheck);
+        play(&mut game, "e4 e5 Bb5 Nc6 Bxc6 dxc6 Qh5 Ke7 Qxe5+ Kd7 Qd5+ Ke7");
+        assert_eq!(game.remaining_checks(Color::White), Some(1));
+        assert_eq!(game.result(), None);
+        play(&mut game, "Qe5+");
+        assert_eq!(game.result(), Some((Outcome::WhiteWins, Termination::ThreeChecks)));
+    }
+
+    #[test]
+    fn atomic_explosion() {
+        let mut game = human_game(Variant::Atomic);
+        play(&mut game, "Nf3 a6 Ng5 a5");
+        assert_eq!(game.result(), None);
+        // The capture next to the king blows it up
+        play(&mut game, "Nxf7");
+        assert_eq!(game.result(), Some((Outcome::WhiteWins, Termination::KingExploded)));
+    }
+
+    #[test]
+    fn crazyhouse_drops() {
+        let mut game = human_game(Variant::Crazyhouse);
+        play(&mut game, "e4 d5 exd5");
+        assert_eq!(game.pocket(Color::White), vec![(Role::Pawn, 1), (Role::Knight, 0), (Role::Bishop, 0), (Role::Rook, 0), (Role::Queen, 0)]);
+        assert!(game.drops(Role::Pawn).is_empty());
+        play(&mut game, "Qxd5 Nc3 Qe6+ Be2");
+        assert!(!game.drops(Role::Pawn).is_empty());
+        // shakmaty leaves out the P of pawn drops
+        play(&mut game, "P@e4");
+        assert_eq!(game.san_moves().last(), Some("@e4"));
+        assert!(game.take_back());
+        assert_eq!(game.san_moves().count(), 7);
+    }
+}
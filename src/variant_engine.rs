@@ -0,0 +1,251 @@
+//! Alpha-beta search for the chess variants. Much simpler than `engine`,
+//! which works on the chess crate and can't play them: iterative deepening
+//! with captures followed to the end, material and a few variant terms.
+
+use shakmaty::Color;
+use shakmaty::Move;
+use shakmaty::Position;
+use shakmaty::Role;
+use shakmaty::Square;
+use shakmaty::variant::VariantPosition;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+// Captures in the quiescence search, Atomic and Crazyhouse have long chains
+const MAX_CAPTURES: u32 = 8;
+const CENTER: [Square; 4] = [Square::D4, Square::E4, Square::D5, Square::E5];
+pub const MAX_DEPTH: u32 = 64;
+
+#[derive(Clone, Debug)]
+pub struct VariantSearchInfo {
+    // In pawns from White's point of view, mates as in `engine`
+    pub eval: f32,
+    pub depth: u32,
+    pub nodes: u64,
+    pub pv: Vec<Move>,
+}
+
+struct Search<'a> {
+    deadline: Instant,
+    stop: &'a AtomicBool,
+    nodes: u64,
+    aborted: bool,
+}
+
+/// Searches `pos` for `time`, up to `depth` or until `stop` is set and
+/// returns the best move of the last finished iteration. `pos` must have a
+/// legal move.
+pub fn best_move(pos: &VariantPosition, time: Duration, depth: u32, stop: &AtomicBool) -> (Move, VariantSearchInfo) {
+    let mut search = Search { deadline: Instant::now() + time, stop, nodes: 0, aborted: false };
+    let mut moves: Vec<Move> = pos.legal_moves().into_iter().collect();
+    order(&mut moves);
+    let mut info = VariantSearchInfo { eval: 0.0, depth: 0, nodes: 0, pv: vec![moves[0].clone()] };
+
+    for depth in 1..=depth.min(MAX_DEPTH) {
+        let mut best: Option<(f32, Vec<Move>)> = None;
+        for m in &moves {
+            let alpha = best.as_ref().map_or(-f32::INFINITY, |(score, _)| *score);
+            let mut child = pos.clone();
+            child.play_unchecked(m);
+            let mut pv = Vec::new();
+            let score = -search.negamax(&child, depth - 1, 1, -f32::INFINITY, -alpha, &mut pv);
+            if search.aborted {
+                break;
+            }
+            if best.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+                pv.insert(0, m.clone());
+                best = Some((score, pv));
+            }
+        }
+        // An unfinished iteration only counts if its first move was searched completely
+        if let Some((score, pv)) = best {
+            let factor = match pos.turn() {
+                Color::White => 1.0,
+                Color::Black => -1.0,
+            };
+            info = VariantSearchInfo { eval: factor * score, depth, nodes: search.nodes, pv };
+            // The best move is searched first in the next iteration
+            let first = moves.iter().position(|m| *m == info.pv[0]).unwrap();
+            moves[..=first].rotate_right(1);
+        }
+        if search.aborted || info.eval.abs() >= 1000.0 {
+            break;
+        }
+    }
+    info.nodes = search.nodes;
+    (info.pv[0].clone(), info)
+}
+
+impl Search<'_> {
+    // Scores from the point of view of the side to move, `pv` receives the best line
+    fn negamax(&mut self, pos: &VariantPosition, depth: u32, ply: u32, mut alpha: f32, beta: f32, pv: &mut Vec<Move>) -> f32 {
+        self.nodes += 1;
+        if self.nodes.is_multiple_of(1024) && (self.stop.load(Ordering::Relaxed) || Instant::now() >= self.deadline) {
+            self.aborted = true;
+        }
+        if self.aborted {
+            return 0.0;
+        }
+        if let Some(score) = final_score(pos, ply) {
+            return score;
+        }
+        if depth == 0 {
+            return self.quiescence(pos, ply, alpha, beta, MAX_CAPTURES);
+        }
+
+        let mut moves: Vec<Move> = pos.legal_moves().into_iter().collect();
+        order(&mut moves);
+        let mut best = -f32::INFINITY;
+        for m in moves {
+            let mut child = pos.clone();
+            child.play_unchecked(&m);
+            let mut line = Vec::new();
+            let score = -self.negamax(&child, depth - 1, ply + 1, -beta, -alpha, &mut line);
+            if score > best {
+                best = score;
+                pv.clear();
+                pv.push(m);
+                pv.extend(line);
+            }
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    fn quiescence(&mut self, pos: &VariantPosition, ply: u32, mut alpha: f32, beta: f32, captures: u32) -> f32 {
+        self.nodes += 1;
+        let stand_pat = evaluate(pos);
+        if stand_pat >= beta || captures == 0 {
+            return stand_pat;
+        }
+        alpha = alpha.max(stand_pat);
+        let mut moves: Vec<Move> = pos.legal_moves().into_iter().filter(|m| m.is_capture()).collect();
+        order(&mut moves);
+        for m in moves {
+            let mut child = pos.clone();
+            child.play_unchecked(&m);
+            let score = match final_score(&child, ply + 1) {
+                Some(score) => -score,
+                None => -self.quiescence(&child, ply + 1, -beta, -alpha, captures - 1),
+            };
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        alpha
+    }
+}
+
+// Score of a finished game from the point of view of the side to move.
+// Mates are scored 1000 + 4000 / (plies + 1) like in `engine`.
+fn final_score(pos: &VariantPosition, ply: u32) -> Option<f32> {
+    let mate = 1000.0 + 4000.0 / (ply as f32 + 1.0);
+    match pos.outcome()? {
+        shakmaty::Outcome::Decisive { winner } if winner == pos.turn() => Some(mate),
+        shakmaty::Outcome::Decisive { .. } => Some(-mate),
+        shakmaty::Outcome::Draw => Some(0.0),
+    }
+}
+
+// Captures of valuable pieces with cheap ones first
+fn order(moves: &mut [Move]) {
+    moves.sort_by_key(|m| {
+        let victim = m.capture().map_or(0.0, value);
+        let attacker = if m.is_capture() { value(m.role()) } else { 0.0 };
+        let promotion = m.promotion().map_or(0.0, value);
+        (-(victim * 10.0 - attacker + promotion) * 10.0) as i32
+    });
+}
+
+fn value(role: Role) -> f32 {
+    match role {
+        Role::Pawn => 1.0,
+        Role::Knight => 3.0,
+        Role::Bishop => 3.2,
+        Role::Rook => 5.0,
+        Role::Queen => 9.0,
+        Role::King => 0.0,
+    }
+}
+
+fn center_distance(square: Square) -> u32 {
+    CENTER.iter().map(|c| c.distance(square)).min().unwrap()
+}
+
+// In pawns from the point of view of the side to move
+fn evaluate(pos: &VariantPosition) -> f32 {
+    let board = pos.board();
+    let mut eval = 0.0;
+    for square in board.occupied() {
+        let piece = board.piece_at(square).unwrap();
+        let sign = match piece.color {
+            Color::White => 1.0,
+            Color::Black => -1.0,
+        };
+        let mut score = value(piece.role);
+        match (piece.role, pos.variant()) {
+            // Getting the king to the center wins the game
+            (Role::King, shakmaty::variant::Variant::KingOfTheHill) => score += (3.0 - center_distance(square) as f32).max(0.0) * 0.5,
+            (Role::King, _) => (),
+            _ => score += (3.0 - center_distance(square) as f32) * 0.03,
+        }
+        eval += sign * score;
+    }
+    // Every check given brings the win closer, the last ones more so
+    if let Some(checks) = pos.remaining_checks() {
+        let given = |color| 3.0 - u32::from(*checks.get(color)) as f32;
+        eval += given(Color::White).powi(2) * 0.8 - given(Color::Black).powi(2) * 0.8;
+    }
+    // Pieces in hand are worth a bit more than on the board, they can go anywhere
+    if let Some(pockets) = pos.pockets() {
+        for role in crate::variant::POCKET_ROLES {
+            eval += (*pockets.white.get(role) as f32 - *pockets.black.get(role) as f32) * value(role) * 1.1;
+        }
+    }
+    match pos.turn() {
+        Color::White => eval,
+        Color::Black => -eval,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shakmaty::san::SanPlus;
+
+    fn position(variant: crate::variant::Variant, moves: &str) -> VariantPosition {
+        let mut pos = variant.start();
+        for san in moves.split_whitespace() {
+            let m = san.parse::<SanPlus>().unwrap().san.to_move(&pos).unwrap();
+            pos.play_unchecked(&m);
+        }
+        pos
+    }
+
+    // The position after the best move
+    fn search(pos: &VariantPosition) -> VariantPosition {
+        let (m, info) = best_move(pos, Duration::from_secs(3600), 3, &AtomicBool::new(false));
+        assert!(info.eval >= 1000.0 && info.depth <= 3, "{:?}", info);
+        let mut after = pos.clone();
+        after.play_unchecked(&m);
+        after
+    }
+
+    #[test]
+    fn finds_variant_wins() {
+        let koth = position(crate::variant::Variant::KingOfTheHill, "e3 a6 Ke2 a5 Kd3 a4");
+        assert!(search(&koth).is_variant_end());
+        let atomic = position(crate::variant::Variant::Atomic, "Nf3 a6 Ng5 a5");
+        assert!(search(&atomic).is_variant_end());
+        // Back rank mate by dropping the rook from the pocket
+        let fen: shakmaty::fen::Fen = "6k1/5ppp/8/8/8/8/5PPP/6K1[R] w - - 0 1".parse().unwrap();
+        let crazyhouse = VariantPosition::from_setup(shakmaty::variant::Variant::Crazyhouse, fen.into_setup(), shakmaty::CastlingMode::Standard).unwrap();
+        assert!(search(&crazyhouse).is_checkmate());
+    }
+}